@@ -0,0 +1,43 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AiState {
+    // Walk back and forth between two x coordinates.
+    Patrol { left: f32, right: f32 },
+    Chase,
+    Charge,
+    Ranged,
+}
+
+// The bottom of the stack is the enemy's default behavior, reactions like chasing the hero are
+// pushed on top and popped once they're over.
+pub struct AiStack {
+    states: Vec<AiState>,
+}
+
+impl AiStack {
+
+    pub fn new(base: AiState) -> Self {
+        Self {
+            states: vec![base],
+        }
+    }
+
+    pub fn current(&self) -> AiState {
+        *self.states.last().unwrap()
+    }
+
+    pub fn push(&mut self, state: AiState) {
+        self.states.push(state);
+    }
+
+    pub fn pop(&mut self) {
+        if self.states.len() > 1 {
+            self.states.pop();
+        }
+    }
+
+    // Swap the default behavior, dropping any reaction in progress.
+    pub fn set_base(&mut self, base: AiState) {
+        self.states.clear();
+        self.states.push(base);
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+use macroquad::*;
+
+// A texture along with how many of its pixels cover one world pixel, 2 for an @2x asset.
+#[derive(Clone, Copy)]
+pub struct Sprite {
+    pub texture: Texture2D,
+    pub density: f32,
+}
+
+impl Sprite {
+
+    // Size in world pixels.
+    pub fn width(&self) -> f32 {
+        self.texture.width() / self.density
+    }
+
+    pub fn height(&self) -> f32 {
+        self.texture.height() / self.density
+    }
+}
+
+// `path` with `@<scale>x` before its extension, so `hero.png` at scale 2 is `hero@2x.png`. At
+// scale 1 the path is unchanged.
+pub fn scaled_path(path: &str, scale: u32) -> String {
+    if scale <= 1 {
+        return path.to_string();
+    }
+    match path.rfind('.') {
+        Some(dot) if !path[dot..].contains('/') => format!("{}@{}x{}", &path[..dot], scale, &path[dot..]),
+        _ => format!("{}@{}x", path, scale),
+    }
+}
+
+// The `scale` variant of `path` when `exists` finds it, otherwise the plain @1x path, along with
+// the scale of the one picked.
+pub fn select_asset(path: &str, scale: u32, exists: impl Fn(&str) -> bool) -> (String, u32) {
+    let scaled = scaled_path(path, scale);
+    if scale > 1 && exists(&scaled) {
+        (scaled, scale)
+    } else {
+        (path.to_string(), 1)
+    }
+}
+
+pub async fn load_sprite(path: &str, scale: u32) -> Sprite {
+    let (path, density) = select_asset(path, scale, |path| Path::new(path).exists());
+    Sprite {
+        texture: load_texture(&path).await,
+        density: density as f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_path_goes_before_the_extension() {
+        assert_eq!(scaled_path("assets/hero.png", 1), "assets/hero.png");
+        assert_eq!(scaled_path("assets/hero.png", 2), "assets/hero@2x.png");
+        assert_eq!(scaled_path("assets.d/hero", 2), "assets.d/hero@2x");
+    }
+
+    #[test]
+    fn picks_the_2x_asset_when_there_is_one_and_falls_back_to_1x() {
+        let only = |present: &'static str| move |path: &str| path == present;
+        assert_eq!(select_asset("hero.png", 2, only("hero@2x.png")), ("hero@2x.png".to_string(), 2));
+        assert_eq!(select_asset("hero.png", 2, only("hero.png")), ("hero.png".to_string(), 1));
+        assert_eq!(select_asset("hero.png", 1, only("hero@2x.png")), ("hero.png".to_string(), 1));
+    }
+}
@@ -0,0 +1,267 @@
+use nalgebra as na;
+use ncollide2d::bounding_volume::AABB;
+
+// What happens to the attacker's horizontal velocity when the swing starts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MomentumBehavior {
+    Keep,
+    Halt,
+    // Multiply the velocity by the given factor.
+    Dampen(f32),
+}
+
+impl MomentumBehavior {
+
+    pub fn apply(self, velocity_x: f32) -> f32 {
+        match self {
+            MomentumBehavior::Keep => velocity_x,
+            MomentumBehavior::Halt => 0.,
+            MomentumBehavior::Dampen(factor) => velocity_x * factor,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AttackDef {
+    pub damage: u32,
+    // Hitbox center relative to the attacker when facing right, mirrored when facing left.
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub half_width: f32,
+    pub half_height: f32,
+    pub animation: usize,
+    pub momentum: MomentumBehavior,
+    // Bounce the attacker upwards when the swing connects.
+    pub bounce_on_hit: bool,
+    // Ticks before the attacker can act again, shorter when the swing connected so landing hits
+    // feels responsive.
+    pub recovery_on_hit: u32,
+    pub recovery_on_whiff: u32,
+    // Speed the swing pushes a struck enemy away from the attacker, before the enemy's resistance.
+    pub knockback: f32,
+    // Forward speed added to the attacker `lunge_tick` ticks into the swing, toward where he
+    // faced when it started. No lunge when zero.
+    pub lunge: f32,
+    pub lunge_tick: u32,
+}
+
+pub const HERO_ATTACK: AttackDef = AttackDef {
+    damage: 1,
+    offset_x: 15.,
+    offset_y: 0.,
+    half_width: 10.,
+    half_height: 10.,
+    animation: 2,
+    momentum: MomentumBehavior::Halt,
+    bounce_on_hit: false,
+    recovery_on_hit: 10,
+    recovery_on_whiff: 20,
+    knockback: 50.,
+    lunge: 30.,
+    lunge_tick: 2,
+};
+
+pub const HERO_AIR_ATTACK: AttackDef = AttackDef {
+    damage: 1,
+    offset_x: 12.,
+    offset_y: 5.,
+    half_width: 12.,
+    half_height: 12.,
+    animation: 3,
+    momentum: MomentumBehavior::Keep,
+    bounce_on_hit: true,
+    recovery_on_hit: 6,
+    recovery_on_whiff: 14,
+    knockback: 30.,
+    lunge: 0.,
+    lunge_tick: 0,
+};
+
+pub const HERO_UP_ATTACK: AttackDef = AttackDef {
+    damage: 1,
+    offset_x: 0.,
+    offset_y: -20.,
+    half_width: 12.,
+    half_height: 8.,
+    animation: 2,
+    momentum: MomentumBehavior::Halt,
+    bounce_on_hit: false,
+    recovery_on_hit: 10,
+    recovery_on_whiff: 20,
+    knockback: 20.,
+    lunge: 0.,
+    lunge_tick: 0,
+};
+
+// A stab at whatever is right under the hero's feet, reaching no higher than them. Its animation
+// also plays through a ground pound.
+pub const HERO_DOWN_ATTACK: AttackDef = AttackDef {
+    damage: 1,
+    offset_x: 4.,
+    offset_y: 18.,
+    half_width: 12.,
+    half_height: 8.,
+    animation: 2,
+    momentum: MomentumBehavior::Halt,
+    bounce_on_hit: false,
+    recovery_on_hit: 10,
+    recovery_on_whiff: 20,
+    knockback: 20.,
+    lunge: 0.,
+    lunge_tick: 0,
+};
+
+// Second and third swings of the grounded combo, see `combo::HERO_COMBO`.
+pub const HERO_COMBO_SECOND: AttackDef = AttackDef {
+    damage: 1,
+    offset_x: 15.,
+    offset_y: 0.,
+    half_width: 12.,
+    half_height: 10.,
+    animation: 3,
+    momentum: MomentumBehavior::Halt,
+    bounce_on_hit: false,
+    recovery_on_hit: 10,
+    recovery_on_whiff: 20,
+    knockback: 30.,
+    lunge: 30.,
+    lunge_tick: 2,
+};
+
+pub const HERO_COMBO_FINISHER: AttackDef = AttackDef {
+    damage: 2,
+    offset_x: 18.,
+    offset_y: 0.,
+    half_width: 14.,
+    half_height: 12.,
+    animation: 2,
+    momentum: MomentumBehavior::Dampen(0.5),
+    bounce_on_hit: false,
+    recovery_on_hit: 10,
+    recovery_on_whiff: 20,
+    knockback: 90.,
+    lunge: 50.,
+    lunge_tick: 3,
+};
+
+// The attack data files like combos refer to by `name`.
+pub fn attack_by_name(name: &str) -> Option<&'static AttackDef> {
+    match name {
+        "attack" => Some(&HERO_ATTACK),
+        "air_attack" => Some(&HERO_AIR_ATTACK),
+        "up_attack" => Some(&HERO_UP_ATTACK),
+        "down_attack" => Some(&HERO_DOWN_ATTACK),
+        "combo_second" => Some(&HERO_COMBO_SECOND),
+        "combo_finisher" => Some(&HERO_COMBO_FINISHER),
+        _ => None,
+    }
+}
+
+// Landing on something faster than this counts as a stomp.
+pub const STOMP_SPEED: f32 = 5.;
+pub const STOMP_DAMAGE: u32 = 1;
+// Upward speed given to the hero after stomping an enemy.
+pub const STOMP_BOUNCE: f32 = 20.;
+
+// Down + attack in the air slams the hero straight down at this speed.
+pub const POUND_SPEED: f32 = 120.;
+pub const POUND_DAMAGE: u32 = 2;
+// Half extents of the shockwave around the hero's feet on landing.
+pub const POUND_RADIUS: f32 = 40.;
+pub const POUND_HEIGHT: f32 = 15.;
+pub const POUND_RECOVERY: u32 = 20;
+// Tiles with their centre this close to where a ground pound lands are knocked out.
+pub const POUND_CARVE_RADIUS: f32 = 22.;
+// Seconds enemies caught in the shockwave stay stunned.
+pub const POUND_STUN: f32 = 2.;
+// Speed the hero is pushed back with when an enemy blocks his attack.
+pub const BLOCK_PUSHBACK: f32 = 60.;
+
+// Holding Up while attacking swings overhead and holding Down sweeps low. Down + attack only
+// gets here on the ground, in the air it's a ground pound instead.
+pub fn hero_attack(up: bool, down: bool, grounded: bool) -> &'static AttackDef {
+    if up {
+        &HERO_UP_ATTACK
+    } else if down {
+        &HERO_DOWN_ATTACK
+    } else if grounded {
+        &HERO_ATTACK
+    } else {
+        &HERO_AIR_ATTACK
+    }
+}
+
+impl AttackDef {
+
+    pub fn recovery(&self, connected: bool) -> u32 {
+        if connected { self.recovery_on_hit } else { self.recovery_on_whiff }
+    }
+
+    pub fn hitbox(&self, position: na::Vector2<f32>, flip: bool) -> AABB<f32> {
+        let direction = if flip { -1. } else { 1. };
+        let center = na::Point2::new(position.x + direction * self.offset_x, position.y + self.offset_y);
+        let half_extents = na::Vector2::new(self.half_width, self.half_height);
+        AABB::new(center - half_extents, center + half_extents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::colliders_in_aabb;
+    use crate::physics::testing::TestWorld;
+
+    // Bottom of a 10 px half-height body's collider at `y`, which sits 1 px above the body.
+    fn feet(y: f32) -> f32 {
+        y - 1. + 10.
+    }
+
+    #[test]
+    fn momentum_behaviors_set_the_horizontal_velocity() {
+        assert_eq!(MomentumBehavior::Keep.apply(40.), 40.);
+        assert_eq!(MomentumBehavior::Halt.apply(40.), 0.);
+        assert_eq!(MomentumBehavior::Dampen(0.25).apply(-40.), -10.);
+    }
+
+    #[test]
+    fn recovery_depends_on_whether_the_swing_connected() {
+        assert_eq!(HERO_ATTACK.recovery(true), HERO_ATTACK.recovery_on_hit);
+        assert_eq!(HERO_ATTACK.recovery(false), HERO_ATTACK.recovery_on_whiff);
+        assert!(HERO_ATTACK.recovery(true) < HERO_ATTACK.recovery(false));
+    }
+
+    #[test]
+    fn up_attack_hits_above_and_down_attack_below() {
+        let position = na::Vector2::new(100., 100.);
+        let up = hero_attack(true, false, true).hitbox(position, false);
+        assert!(up.maxs().y < position.y);
+        assert!(up.mins().x < position.x && up.maxs().x > position.x);
+        let down = hero_attack(false, true, true).hitbox(position, false);
+        assert!(down.mins().y >= feet(position.y), "{:?} reaches above the feet", down);
+        assert!(down.center().x > position.x);
+        let down_left = hero_attack(false, true, true).hitbox(position, true);
+        assert_eq!(down_left.center().x - position.x, position.x - down.center().x);
+        assert_eq!(down_left.center().y, down.center().y);
+    }
+
+    #[test]
+    fn down_attack_hits_an_enemy_underfoot_but_not_one_at_the_waist() {
+        let mut world = TestWorld::new();
+        let hero = world.fixed(0., 0., 10., 10.);
+        let below = world.fixed(0., 20., 10., 10.);
+        let beside = world.fixed(20., 0., 10., 10.);
+        world.step();
+        let hitbox = HERO_DOWN_ATTACK.hitbox(hero.position(&world.bodies), false);
+        let hit = colliders_in_aabb(&world.geometrical_world, &world.colliders, &hitbox);
+        assert!(hit.contains(&below.collider_handle));
+        assert!(!hit.contains(&beside.collider_handle));
+        assert!(!hit.contains(&hero.collider_handle));
+    }
+
+    #[test]
+    fn no_direction_picks_the_ground_or_air_attack() {
+        assert_eq!(hero_attack(false, false, true), &HERO_ATTACK);
+        assert_eq!(hero_attack(false, false, false), &HERO_AIR_ATTACK);
+        assert_eq!(hero_attack(true, false, false), &HERO_UP_ATTACK);
+    }
+}
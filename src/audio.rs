@@ -0,0 +1,74 @@
+use crate::settings::UserSettings;
+
+// Sound effects the game asks for. macroquad 0.2 has no audio playback, so for now requests are
+// only queued; a backend drains the queue once there is one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sound {
+    // Something hitting level geometry.
+    Land,
+    // Two loose objects knocking together.
+    Thud,
+}
+
+pub struct AudioManager {
+    // Master times effects volume from the settings.
+    pub volume: f32,
+    // Master times music volume, for the backend to play music at.
+    pub music_volume: f32,
+    queue: Vec<(Sound, f32)>,
+}
+
+impl AudioManager {
+
+    pub fn new(settings: &UserSettings) -> Self {
+        let mut audio = Self {
+            volume: 0.,
+            music_volume: 0.,
+            queue: Vec::new(),
+        };
+        audio.set_volumes(settings);
+        audio
+    }
+
+    pub fn set_volumes(&mut self, settings: &UserSettings) {
+        self.volume = settings.master_volume * settings.sfx_volume;
+        self.music_volume = settings.master_volume * settings.music_volume;
+    }
+
+    // `volume` is relative to the effects volume, 0 to 1.
+    pub fn play(&mut self, sound: Sound, volume: f32) {
+        if self.volume > 0. && volume > 0. {
+            self.queue.push((sound, self.volume * volume.min(1.)));
+        }
+    }
+
+    // Sounds requested since the last call, with their final volume.
+    pub fn drain(&mut self) -> Vec<(Sound, f32)> {
+        std::mem::take(&mut self.queue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_volumes_apply_to_the_audio_manager() {
+        let path = std::env::temp_dir().join("new_game_audio_settings_test.cfg");
+        let settings = UserSettings {
+            master_volume: 0.5,
+            music_volume: 0.4,
+            sfx_volume: 0.6,
+            ..UserSettings::default()
+        };
+        settings.save(&path).unwrap();
+        let loaded = UserSettings::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut audio = AudioManager::new(&UserSettings::default());
+        audio.set_volumes(&loaded);
+        assert!((audio.volume - 0.3).abs() < 1e-6);
+        assert!((audio.music_volume - 0.2).abs() < 1e-6);
+        assert_eq!(audio.volume, AudioManager::new(&loaded).volume);
+    }
+}
@@ -0,0 +1,72 @@
+use macroquad::*;
+use nphysics2d::object::{DefaultBodySet, DefaultColliderSet};
+use crate::GameObject;
+use crate::particles::Particles;
+
+pub struct BreakableBlock {
+    pub object: GameObject,
+    pub health: u32,
+}
+
+const DEBRIS_COUNT: usize = 12;
+
+impl BreakableBlock {
+
+    pub fn new(x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, width: f32, height: f32, health: u32) -> Self {
+        Self {
+            object: GameObject::new(x, y, bodies, colliders, width, height, 0., 0.),
+            health,
+        }
+    }
+
+    // Returns the damage actually taken.
+    pub fn hit(&mut self, damage: u32) -> u32 {
+        let health = self.health;
+        self.health = self.health.saturating_sub(damage);
+        health - self.health
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.health == 0
+    }
+
+    pub fn shatter(self, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, particles: &mut Particles) {
+        let pos = self.object.rigid_body(bodies).position().translation.vector;
+        particles.burst(pos.x, pos.y, DEBRIS_COUNT, BROWN);
+        colliders.remove(self.object.collider_handle);
+        bodies.remove(self.object.body_handle);
+    }
+
+    pub fn debug_draw(&self, bodies: &DefaultBodySet<f32>) {
+        self.object.debug_draw(bodies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::TestWorld;
+
+    #[test]
+    fn enough_damage_breaks_and_despawns_the_block() {
+        let mut world = TestWorld::new();
+        let mut particles = Particles::new();
+        let mut block = BreakableBlock::new(0., 0., &mut world.bodies, &mut world.colliders, 10., 10., 3);
+        let (body, collider) = (block.object.body_handle, block.object.collider_handle);
+        assert_eq!(block.hit(5), 3);
+        assert!(block.is_broken());
+        block.shatter(&mut world.bodies, &mut world.colliders, &mut particles);
+        assert!(world.bodies.get(body).is_none());
+        assert!(world.colliders.get(collider).is_none());
+    }
+
+    #[test]
+    fn weak_hits_leave_a_multi_hit_block_standing() {
+        let mut world = TestWorld::new();
+        let mut block = BreakableBlock::new(0., 0., &mut world.bodies, &mut world.colliders, 10., 10., 3);
+        assert_eq!(block.hit(1), 1);
+        assert_eq!(block.hit(1), 1);
+        assert!(!block.is_broken());
+        assert_eq!(block.health, 1);
+    }
+}
@@ -0,0 +1,390 @@
+use macroquad::*;
+use nphysics2d::object::{DefaultBodyHandle, DefaultBodySet};
+use crate::settings::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+// How long Up/Down has to be held before the camera starts peeking.
+const PEEK_DELAY: f32 = 0.5;
+const PEEK_DISTANCE: f32 = 120.;
+const PEEK_SPEED: f32 = 4.;
+// How quickly the camera catches up with the hero on each axis, per second. Vertical tracking
+// is lazier so jumps don't bob the view.
+const SMOOTH_X: f32 = 10.;
+const SMOOTH_Y: f32 = 3.;
+// With `grounded_follow`, the camera still follows vertically mid-air once the hero is this far
+// from where he last stood, e.g. when falling down a pit.
+const AIRBORNE_FOLLOW_DISTANCE: f32 = 80.;
+// Seconds the camera takes to pan over when the hero walks into another room.
+const ROOM_TRANSITION: f32 = 0.6;
+
+// What the camera follows. Anything but the hero is followed directly, without the grounded
+// follow or peeking, and a body that no longer exists falls back to the hero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FocusTarget {
+    Hero,
+    Body(DefaultBodyHandle),
+    Point(Vec2),
+}
+
+pub struct FollowCamera {
+    pub target: Vec2,
+    focus: FocusTarget,
+    // Seconds left before the focus goes back to the hero, None to keep it until changed.
+    focus_time: Option<f32>,
+    pub zoom: f32,
+    // Window size in screen pixels, kept up to date by the game loop.
+    pub screen: Vec2,
+    // Whole screen pixels per world pixel at zoom 1, on top of `zoom`. See `integer_scale`.
+    pub render_scale: u32,
+    // Round the camera and sprite positions to whole world pixels so pixel art doesn't shimmer.
+    pub pixel_snap: bool,
+    pub smooth_x: f32,
+    pub smooth_y: f32,
+    // Only follow the hero vertically while he is grounded, so jumps don't move the view.
+    pub grounded_follow: bool,
+    // With rooms set the view is kept inside the room the hero is in instead of following him
+    // freely. Leaving every room keeps the last one.
+    pub rooms: Vec<Rect>,
+    pub room: Option<usize>,
+    // Outside every room the view is still kept inside these, usually the whole level.
+    pub bounds: Option<Rect>,
+    // Where the view was when the room changed and how far the pan to the new room has got, 0 to 1.
+    transition_from: Vec2,
+    transition: f32,
+    // Height the camera follows vertically, the hero's y when he last stood on something.
+    anchor_y: f32,
+    // Smoothed hero position, before the peek offset is added.
+    follow: Vec2,
+    peek: f32,
+    peek_hold: f32,
+}
+
+impl FollowCamera {
+
+    pub fn new(target: Vec2) -> Self {
+        Self {
+            target,
+            focus: FocusTarget::Hero,
+            focus_time: None,
+            zoom: 1.,
+            screen: vec2(VIRTUAL_WIDTH, VIRTUAL_HEIGHT),
+            render_scale: 1,
+            pixel_snap: false,
+            smooth_x: SMOOTH_X,
+            smooth_y: SMOOTH_Y,
+            grounded_follow: true,
+            rooms: Vec::new(),
+            room: None,
+            bounds: None,
+            transition_from: target,
+            transition: 1.,
+            anchor_y: target.y(),
+            follow: target,
+            peek: 0.,
+            peek_hold: 0.,
+        }
+    }
+
+    // Follows `focus` instead of the hero for `duration` seconds, or until it's set again.
+    pub fn set_focus(&mut self, focus: FocusTarget, duration: Option<f32>) {
+        self.focus = focus;
+        self.focus_time = duration;
+    }
+
+    // Moves toward whatever is in focus, the hero at `hero` by default. `peek` is -1 to look up,
+    // 1 to look down and 0 when not peeking.
+    pub fn follow(&mut self, delta: f32, bodies: &DefaultBodySet<f32>, hero: Vec2, grounded: bool, peek: f32) {
+        if let Some(time) = &mut self.focus_time {
+            *time -= delta;
+            if *time <= 0. {
+                self.set_focus(FocusTarget::Hero, None);
+            }
+        }
+        let focus = match self.focus {
+            FocusTarget::Hero => None,
+            FocusTarget::Body(handle) => bodies.rigid_body(handle).map(|body| {
+                let position = body.position().translation.vector;
+                vec2(position.x, position.y)
+            }),
+            FocusTarget::Point(point) => Some(point),
+        };
+        match focus {
+            Some(focus) => self.update(delta, focus, true, 0.),
+            None => self.update(delta, hero, grounded, peek),
+        }
+    }
+
+    fn update(&mut self, delta: f32, focus: Vec2, grounded: bool, peek: f32) {
+        let previous = self.target;
+        if peek != 0. {
+            self.peek_hold += delta;
+        } else {
+            self.peek_hold = 0.;
+        }
+        let desired = if self.peek_hold >= PEEK_DELAY { peek * PEEK_DISTANCE } else { 0. };
+        self.peek += (desired - self.peek) * (PEEK_SPEED * delta).min(1.);
+        if !self.grounded_follow || grounded || (focus.y() - self.anchor_y).abs() > AIRBORNE_FOLLOW_DISTANCE {
+            self.anchor_y = focus.y();
+        }
+        let offset = vec2(focus.x(), self.anchor_y) - self.follow;
+        self.follow += vec2(offset.x() * (self.smooth_x * delta).min(1.), offset.y() * (self.smooth_y * delta).min(1.));
+        self.target = self.follow + vec2(0., self.peek);
+        self.frame_room(delta, focus, previous);
+    }
+
+    // Keeps the view inside the hero's room, or the level bounds, panning over from `previous` when he changes rooms.
+    fn frame_room(&mut self, delta: f32, focus: Vec2, previous: Vec2) {
+        let entered = self.rooms.iter().position(|room| {
+            focus.x() >= room.x && focus.x() <= room.x + room.w && focus.y() >= room.y && focus.y() <= room.y + room.h
+        });
+        if entered.is_some() && entered != self.room {
+            if self.room.is_some() {
+                self.transition_from = previous;
+                self.transition = 0.;
+            }
+            self.room = entered;
+        }
+        let room = match self.room.map(|room| &self.rooms[room]).or(self.bounds.as_ref()) {
+            Some(room) => room,
+            None => return,
+        };
+        let half_view = self.screen / (2. * self.scale());
+        let framed = vec2(
+            clamp_to_room(self.target.x(), room.x, room.w, half_view.x()),
+            clamp_to_room(self.target.y(), room.y, room.h, half_view.y()),
+        );
+        self.transition = (self.transition + delta / ROOM_TRANSITION).min(1.);
+        self.target = self.transition_from + (framed - self.transition_from) * self.transition;
+    }
+
+    // Maps a pixel position on screen, like the mouse cursor, into the world.
+    pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
+        let ndc = vec2(point.x() / self.screen.x() * 2. - 1., 1. - point.y() / self.screen.y() * 2.);
+        self.camera().screen_to_world(ndc)
+    }
+
+    fn scale(&self) -> f32 {
+        self.zoom * self.render_scale as f32
+    }
+
+    pub fn camera(&self) -> Camera2D {
+        Camera2D {
+            zoom: vec2(2. * self.scale() / self.screen.x(), -2. * self.scale() / self.screen.y()),
+            target: if self.pixel_snap { snap_to_pixel(self.target) } else { self.target },
+            ..Default::default()
+        }
+    }
+}
+
+// Centre of a view `half_view` wide along one axis, as close to `center` as fits in the room
+// spanning `start` to `start + length`. A room smaller than the view is centred instead.
+fn clamp_to_room(center: f32, start: f32, length: f32, half_view: f32) -> f32 {
+    if length <= half_view * 2. {
+        start + length / 2.
+    } else {
+        center.clamp(start + half_view, start + length - half_view)
+    }
+}
+
+// Largest whole factor the `virtual_size` view can be scaled up by and still fit in `window`,
+// at least 1 when the window is smaller than the view.
+pub fn integer_scale(window: Vec2, virtual_size: Vec2) -> u32 {
+    let fit = (window.x() / virtual_size.x()).min(window.y() / virtual_size.y());
+    (fit.floor() as u32).max(1)
+}
+
+// Whole factor the camera scales the `virtual_size` view by in `window` for the `render_scale`
+// setting, where 0 picks the largest one that fits.
+pub fn render_scale(setting: u32, window: Vec2, virtual_size: Vec2) -> u32 {
+    match setting {
+        0 => integer_scale(window, virtual_size),
+        scale => scale,
+    }
+}
+
+// The `render_scale` setting the toggle key switches to: fitting the window from any fixed
+// scale, and 1x from fitting.
+pub fn toggled_render_scale(setting: u32) -> u32 {
+    if setting == 0 { 1 } else { 0 }
+}
+
+pub fn snap_to_pixel(position: Vec2) -> Vec2 {
+    vec2(position.x().round(), position.y().round())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::{TestWorld, DT};
+
+    // Where the camera ends up after `seconds` of following the hero standing at `hero`.
+    fn follow_for(camera: &mut FollowCamera, bodies: &DefaultBodySet<f32>, hero: Vec2, seconds: f32) {
+        for _ in 0..(seconds / DT) as usize {
+            camera.follow(DT, bodies, hero, true, 0.);
+        }
+    }
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!((actual - expected).length() < 0.5, "{:?} is not {:?}", actual, expected);
+    }
+
+    fn peek_for(camera: &mut FollowCamera, bodies: &DefaultBodySet<f32>, peek: f32, seconds: f32) {
+        for _ in 0..(seconds / DT).round() as usize {
+            camera.follow(DT, bodies, vec2(0., 0.), true, peek);
+        }
+    }
+
+    #[test]
+    fn peek_shifts_down_after_the_delay_and_returns_on_release() {
+        let world = TestWorld::new();
+        let mut camera = FollowCamera::new(vec2(0., 0.));
+        peek_for(&mut camera, &world.bodies, 1., PEEK_DELAY - 0.1);
+        assert_eq!(camera.target.y(), 0.);
+        peek_for(&mut camera, &world.bodies, 1., 3.);
+        assert!((camera.target.y() - PEEK_DISTANCE).abs() < 1., "peeking at {}", camera.target.y());
+        peek_for(&mut camera, &world.bodies, 0., 0.1);
+        assert!(camera.target.y() > 0. && camera.target.y() < PEEK_DISTANCE, "snapped back to {}", camera.target.y());
+        peek_for(&mut camera, &world.bodies, 0., 3.);
+        assert!(camera.target.y().abs() < 1., "still peeking at {}", camera.target.y());
+    }
+
+    fn follow_hero(camera: &mut FollowCamera, bodies: &DefaultBodySet<f32>, hero: Vec2, grounded: bool, ticks: usize) {
+        for _ in 0..ticks {
+            camera.follow(DT, bodies, hero, grounded, 0.);
+        }
+    }
+
+    #[test]
+    fn camera_tracks_x_quickly_and_y_slowly() {
+        let world = TestWorld::new();
+        let mut camera = FollowCamera::new(vec2(0., 0.));
+        camera.smooth_x = 10.;
+        camera.smooth_y = 2.;
+        follow_hero(&mut camera, &world.bodies, vec2(100., 100.), true, 15);
+        let (x, y) = (camera.target.x(), camera.target.y());
+        assert!(x > 90., "x only got to {}", x);
+        assert!(y > 10. && y < 50., "y got to {}", y);
+    }
+
+    #[test]
+    fn jumps_do_not_move_the_camera_but_landing_higher_does() {
+        let world = TestWorld::new();
+        let mut camera = FollowCamera::new(vec2(0., 0.));
+        follow_hero(&mut camera, &world.bodies, vec2(0., -40.), false, 60);
+        assert_eq!(camera.target.y(), 0.);
+        follow_hero(&mut camera, &world.bodies, vec2(0., -40.), true, 180);
+        assert!((camera.target.y() + 40.).abs() < 0.5, "camera at {}", camera.target.y());
+    }
+
+    #[test]
+    fn falling_far_is_followed_mid_air() {
+        let world = TestWorld::new();
+        let mut camera = FollowCamera::new(vec2(0., 0.));
+        follow_hero(&mut camera, &world.bodies, vec2(0., AIRBORNE_FOLLOW_DISTANCE + 20.), false, 180);
+        assert!((camera.target.y() - AIRBORNE_FOLLOW_DISTANCE - 20.).abs() < 0.5, "camera at {}", camera.target.y());
+    }
+
+    #[test]
+    fn focus_point_is_followed_instead_of_the_hero() {
+        let world = TestWorld::new();
+        let mut camera = FollowCamera::new(vec2(0., 0.));
+        camera.set_focus(FocusTarget::Point(vec2(200., 50.)), None);
+        follow_for(&mut camera, &world.bodies, vec2(0., 0.), 5.);
+        assert_near(camera.target, vec2(200., 50.));
+    }
+
+    #[test]
+    fn timed_focus_goes_back_to_the_hero() {
+        let world = TestWorld::new();
+        let mut camera = FollowCamera::new(vec2(0., 0.));
+        camera.set_focus(FocusTarget::Point(vec2(200., 50.)), Some(1.));
+        follow_for(&mut camera, &world.bodies, vec2(0., 0.), 1.5);
+        assert_eq!(camera.focus, FocusTarget::Hero);
+        follow_for(&mut camera, &world.bodies, vec2(0., 0.), 5.);
+        assert_near(camera.target, vec2(0., 0.));
+    }
+
+    #[test]
+    fn focus_body_follows_it_until_it_is_gone() {
+        let mut world = TestWorld::new();
+        let object = world.dynamic(100., 40., 10., 10.);
+        let mut camera = FollowCamera::new(vec2(0., 0.));
+        camera.set_focus(FocusTarget::Body(object.body_handle), None);
+        follow_for(&mut camera, &world.bodies, vec2(0., 0.), 5.);
+        assert_near(camera.target, vec2(100., 40.));
+        world.bodies.remove(object.body_handle);
+        follow_for(&mut camera, &world.bodies, vec2(0., 0.), 5.);
+        assert_near(camera.target, vec2(0., 0.));
+    }
+
+    #[test]
+    fn crossing_into_another_room_pans_over_and_frames_it() {
+        let world = TestWorld::new();
+        let (width, height) = (VIRTUAL_WIDTH, VIRTUAL_HEIGHT);
+        let mut camera = FollowCamera::new(vec2(100., 100.));
+        camera.rooms = vec![Rect::new(0., 0., width, height), Rect::new(width, 0., width, height)];
+        follow_for(&mut camera, &world.bodies, vec2(100., 100.), 1.);
+        assert_eq!(camera.room, Some(0));
+        assert_near(camera.target, vec2(width / 2., height / 2.));
+
+        follow_for(&mut camera, &world.bodies, vec2(width + 100., 100.), ROOM_TRANSITION / 2.);
+        assert_eq!(camera.room, Some(1));
+        assert!(camera.target.x() > width / 2. && camera.target.x() < width * 1.5, "already at {}", camera.target.x());
+        follow_for(&mut camera, &world.bodies, vec2(width + 100., 100.), ROOM_TRANSITION);
+        assert_near(camera.target, vec2(width * 1.5, height / 2.));
+    }
+
+    #[test]
+    fn snap_rounds_to_the_nearest_pixel() {
+        assert_eq!(snap_to_pixel(vec2(10.4, 20.6)), vec2(10., 21.));
+        assert_eq!(snap_to_pixel(vec2(-3.6, 0.49)), vec2(-4., 0.));
+    }
+
+    #[test]
+    fn pixel_snap_rounds_the_camera_target() {
+        let mut camera = FollowCamera::new(vec2(10.4, 20.6));
+        assert_eq!(camera.camera().target, vec2(10.4, 20.6));
+        camera.pixel_snap = true;
+        assert_eq!(camera.camera().target, vec2(10., 21.));
+    }
+
+    #[test]
+    fn integer_scale_picks_the_largest_fit() {
+        let view = vec2(400., 300.);
+        assert_eq!(integer_scale(vec2(400., 300.), view), 1);
+        assert_eq!(integer_scale(vec2(1200., 900.), view), 3);
+        // Limited by the height.
+        assert_eq!(integer_scale(vec2(2000., 700.), view), 2);
+        assert_eq!(integer_scale(vec2(1199., 899.), view), 2);
+    }
+
+    // The scale is worked out again from whatever size the window has, so going fullscreen
+    // scales up without anything else changing.
+    #[test]
+    fn integer_scale_windowed_and_fullscreen() {
+        let view = vec2(VIRTUAL_WIDTH, VIRTUAL_HEIGHT);
+        assert_eq!(integer_scale(vec2(800., 600.), view), 2);
+        assert_eq!(integer_scale(vec2(1920., 1080.), view), 3);
+        assert_eq!(integer_scale(vec2(2560., 1440.), view), 4);
+        assert_eq!(integer_scale(vec2(3840., 2160.), view), 7);
+    }
+
+    #[test]
+    fn toggling_the_scale_refits_windowed_and_fullscreen_sizes() {
+        let view = vec2(VIRTUAL_WIDTH, VIRTUAL_HEIGHT);
+        let (windowed, fullscreen) = (vec2(800., 600.), vec2(1920., 1080.));
+        let fit = toggled_render_scale(1);
+        assert_eq!(fit, 0);
+        assert_eq!(render_scale(fit, windowed, view), 2);
+        assert_eq!(render_scale(fit, fullscreen, view), 3);
+        let fixed = toggled_render_scale(fit);
+        assert_eq!(render_scale(fixed, windowed, view), 1);
+        assert_eq!(render_scale(fixed, fullscreen, view), 1);
+        // A fixed scale other than 1 toggles to fitting too.
+        assert_eq!(toggled_render_scale(3), 0);
+    }
+
+    #[test]
+    fn integer_scale_is_at_least_one() {
+        assert_eq!(integer_scale(vec2(200., 100.), vec2(400., 300.)), 1);
+    }
+}
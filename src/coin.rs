@@ -0,0 +1,59 @@
+use macroquad::*;
+use nalgebra as na;
+use nphysics2d::object::{DefaultBodySet, DefaultColliderSet};
+use crate::GameObject;
+use crate::particles::Particles;
+use crate::physics::set_sensor;
+
+const COIN_RADIUS: f32 = 4.;
+const SPARKLE_COUNT: usize = 6;
+// With the magnet, coins this close to the hero start flying to him, faster every second.
+const MAGNET_RADIUS: f32 = 60.;
+const MAGNET_ACCELERATION: f32 = 400.;
+
+// A pickup the hero collects by touching it. Its collider is a sensor, so it never gets in the way.
+pub struct Coin {
+    pub object: GameObject,
+    // Speed the magnet is pulling the coin at, zero until it gets in range.
+    pull_speed: f32,
+}
+
+impl Coin {
+
+    pub fn new(x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>) -> Self {
+        let object = GameObject::new(x, y, bodies, colliders, COIN_RADIUS, COIN_RADIUS, 0., 0.);
+        set_sensor(colliders, object.collider_handle, true);
+        Self {
+            object,
+            pull_speed: 0.,
+        }
+    }
+
+    // Moves the coin toward `target` once it has come within the magnet's radius. A coin being
+    // pulled keeps coming even if the hero moves away.
+    pub fn attract(&mut self, bodies: &mut DefaultBodySet<f32>, target: na::Vector2<f32>, delta: f32) {
+        let pos = self.object.position(bodies);
+        let offset = target - pos;
+        let distance = offset.norm();
+        if distance <= f32::EPSILON || (self.pull_speed == 0. && distance > MAGNET_RADIUS) {
+            return;
+        }
+        self.pull_speed += MAGNET_ACCELERATION * delta;
+        let position = pos + offset * (self.pull_speed * delta / distance).min(1.);
+        if let Some(body) = bodies.rigid_body_mut(self.object.body_handle) {
+            body.set_position(na::Isometry2::translation(position.x, position.y));
+        }
+    }
+
+    pub fn collect(self, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, particles: &mut Particles) {
+        let pos = self.object.position(bodies);
+        particles.burst(pos.x, pos.y, SPARKLE_COUNT, GOLD);
+        colliders.remove(self.object.collider_handle);
+        bodies.remove(self.object.body_handle);
+    }
+
+    pub fn draw(&self, bodies: &DefaultBodySet<f32>) {
+        let pos = self.object.position(bodies);
+        draw_circle(pos.x, pos.y, COIN_RADIUS, GOLD);
+    }
+}
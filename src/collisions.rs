@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use ncollide2d::pipeline::ContactEvent;
+use ncollide2d::query::Proximity;
+use nphysics2d::object::DefaultColliderHandle;
+use nphysics2d::world::DefaultGeometricalWorld;
+use crate::world::GameWorld;
+
+// What a collider belongs to, as far as gameplay events care.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColliderKind {
+    Hero,
+    Enemy,
+    Coin,
+}
+
+// Called with the colliders in the order the handler was registered for.
+pub type CollisionHandler = fn(&mut GameWorld, DefaultColliderHandle, DefaultColliderHandle);
+
+// Gameplay reactions to two kinds of collider starting to touch, from solid contacts or sensor
+// overlaps alike. The physics events are read once per tick and dispatched from here, instead of
+// every system polling its own contacts.
+pub struct CollisionRegistry {
+    kinds: BTreeMap<DefaultColliderHandle, ColliderKind>,
+    handlers: BTreeMap<(ColliderKind, ColliderKind), Vec<CollisionHandler>>,
+}
+
+impl CollisionRegistry {
+
+    pub fn new() -> Self {
+        Self {
+            kinds: BTreeMap::new(),
+            handlers: BTreeMap::new(),
+        }
+    }
+
+    pub fn tag(&mut self, handle: DefaultColliderHandle, kind: ColliderKind) {
+        self.kinds.insert(handle, kind);
+    }
+
+    pub fn untag(&mut self, handle: DefaultColliderHandle) {
+        self.kinds.remove(&handle);
+    }
+
+    pub fn register(&mut self, a: ColliderKind, b: ColliderKind, handler: CollisionHandler) {
+        self.handlers.entry((a, b)).or_default().push(handler);
+    }
+
+    // Every handler call due for the contacts and overlaps that began during the last step.
+    fn pending(&self, geometrical_world: &DefaultGeometricalWorld<f32>) -> Vec<(CollisionHandler, DefaultColliderHandle, DefaultColliderHandle)> {
+        let started = geometrical_world.contact_events().iter()
+            .filter_map(|event| match event {
+                ContactEvent::Started(a, b) => Some((*a, *b)),
+                ContactEvent::Stopped(..) => None,
+            });
+        let entered = geometrical_world.proximity_events().iter()
+            .filter(|event| event.new_status == Proximity::Intersecting && event.prev_status != Proximity::Intersecting)
+            .map(|event| (event.collider1, event.collider2));
+        let mut calls = Vec::new();
+        for (a, b) in started.chain(entered) {
+            let (kind_a, kind_b) = match (self.kinds.get(&a), self.kinds.get(&b)) {
+                (Some(kind_a), Some(kind_b)) => (*kind_a, *kind_b),
+                _ => continue,
+            };
+            for handler in self.handlers.get(&(kind_a, kind_b)).into_iter().flatten() {
+                calls.push((*handler, a, b));
+            }
+            if kind_a != kind_b {
+                for handler in self.handlers.get(&(kind_b, kind_a)).into_iter().flatten() {
+                    calls.push((*handler, b, a));
+                }
+            }
+        }
+        calls
+    }
+}
+
+pub fn dispatch(world: &mut GameWorld) {
+    for (handler, a, b) in world.collisions.pending(&world.geometrical_world) {
+        handler(world, a, b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use nalgebra as na;
+    use crate::input::InputState;
+    use crate::profile::Profile;
+    use crate::settings::UserSettings;
+
+    thread_local! {
+        static CALLS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    fn count(_world: &mut GameWorld, _hero: DefaultColliderHandle, _coin: DefaultColliderHandle) {
+        CALLS.with(|calls| calls.set(calls.get() + 1));
+    }
+
+    // Leaves the coin where it is, so the hero keeps overlapping it after the first tick.
+    #[test]
+    fn handler_fires_once_when_the_hero_overlaps_a_coin() {
+        let mut world = GameWorld::new(&UserSettings::default(), Profile::default());
+        let mut registry = CollisionRegistry::new();
+        registry.tag(world.hero.object.collider_handle, ColliderKind::Hero);
+        registry.tag(world.coins[0].object.collider_handle, ColliderKind::Coin);
+        registry.register(ColliderKind::Hero, ColliderKind::Coin, count);
+        world.collisions = registry;
+        let coin = world.coins[0].object.position(&world.bodies);
+        let hero = world.bodies.rigid_body_mut(world.hero.object.body_handle).unwrap();
+        hero.set_position(na::Isometry2::translation(coin.x, coin.y));
+        for _ in 0..30 {
+            world.update(1. / 60., &InputState::default());
+        }
+        assert_eq!(CALLS.with(Cell::get), 1);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::attack::{attack_by_name, AttackDef};
+
+// The hero's combo, written in the same `key = value` layout as the settings file.
+pub const HERO_COMBO: &str = include_str!("../assets/hero.combo");
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComboStep {
+    pub attack: &'static AttackDef,
+    // Pressing attack during the last this many ticks of the step's recovery swings the next
+    // step right away. Unused on the final step, which has nothing to cancel into.
+    pub cancel_window: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComboDef {
+    pub steps: Vec<ComboStep>,
+    // Recovery after the final step, replacing its attack's own, so a finished string leaves
+    // the attacker open for a moment.
+    pub final_recovery: u32,
+}
+
+impl ComboDef {
+
+    // Every `step = <attack>, <cancel window>` line adds a step, in order. Steps naming an
+    // unknown attack and any other unknown or unparsable lines are skipped.
+    pub fn parse(text: &str) -> Self {
+        let mut def = Self { steps: Vec::new(), final_recovery: 0 };
+        for line in text.lines() {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            match key {
+                "final_recovery" => def.final_recovery = value.parse().unwrap_or(def.final_recovery),
+                "step" => {
+                    let mut fields = value.splitn(2, ',').map(str::trim);
+                    let attack = fields.next().and_then(attack_by_name);
+                    let cancel_window = fields.next().and_then(|window| window.parse().ok());
+                    if let (Some(attack), Some(cancel_window)) = (attack, cancel_window) {
+                        def.steps.push(ComboStep { attack, cancel_window });
+                    }
+                }
+                _ => {}
+            }
+        }
+        def
+    }
+}
+
+// Where the attacker is in a combo string.
+pub struct Combo {
+    def: ComboDef,
+    step: Option<usize>,
+}
+
+impl Combo {
+
+    pub fn new(def: ComboDef) -> Self {
+        Self {
+            def,
+            step: None,
+        }
+    }
+
+    // Attack to swing for a press with `recovery` ticks of recovery left: the next step inside
+    // the current step's cancel window, the first step once fully recovered, otherwise none.
+    pub fn next(&mut self, recovery: u32) -> Option<&'static AttackDef> {
+        let step = match self.step {
+            _ if recovery == 0 => 0,
+            Some(step) if step + 1 < self.def.steps.len() && recovery <= self.def.steps[step].cancel_window => step + 1,
+            _ => return None,
+        };
+        self.step = Some(step);
+        Some(self.def.steps[step].attack)
+    }
+
+    // Recovery after swinging the current step's attack.
+    pub fn recovery(&self, attack: &AttackDef, connected: bool) -> u32 {
+        match self.step {
+            Some(step) if step + 1 == self.def.steps.len() => self.def.final_recovery,
+            _ => attack.recovery(connected),
+        }
+    }
+
+    // Drops the string, e.g. when a different attack is swung instead.
+    pub fn reset(&mut self) {
+        self.step = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attack::{HERO_AIR_ATTACK, HERO_ATTACK, HERO_COMBO_FINISHER, HERO_COMBO_SECOND, HERO_DOWN_ATTACK, HERO_UP_ATTACK};
+
+    const TEST_COMBO: &str = "final_recovery = 45
+step = up_attack, 5
+step = no_such_attack, 4
+step = down_attack, 3
+step = air_attack, 0
+";
+
+    #[test]
+    fn parses_steps_in_order_and_skips_unknown_attacks() {
+        let def = ComboDef::parse(TEST_COMBO);
+        assert_eq!(def.final_recovery, 45);
+        assert_eq!(def.steps, vec![
+            ComboStep { attack: &HERO_UP_ATTACK, cancel_window: 5 },
+            ComboStep { attack: &HERO_DOWN_ATTACK, cancel_window: 3 },
+            ComboStep { attack: &HERO_AIR_ATTACK, cancel_window: 0 },
+        ]);
+    }
+
+    #[test]
+    fn hero_combo_data_parses() {
+        let def = ComboDef::parse(HERO_COMBO);
+        let attacks: Vec<_> = def.steps.iter().map(|step| step.attack).collect();
+        assert_eq!(attacks, vec![&HERO_ATTACK, &HERO_COMBO_SECOND, &HERO_COMBO_FINISHER]);
+        assert_eq!(def.final_recovery, 30);
+    }
+
+    #[test]
+    fn data_defined_combo_swings_each_step_in_order() {
+        let mut combo = Combo::new(ComboDef::parse(TEST_COMBO));
+        assert_eq!(combo.next(0), Some(&HERO_UP_ATTACK));
+        assert_eq!(combo.recovery(&HERO_UP_ATTACK, true), HERO_UP_ATTACK.recovery_on_hit);
+        // Too early for the first step's window.
+        assert_eq!(combo.next(6), None);
+        assert_eq!(combo.next(5), Some(&HERO_DOWN_ATTACK));
+        assert_eq!(combo.next(4), None);
+        assert_eq!(combo.next(3), Some(&HERO_AIR_ATTACK));
+        assert_eq!(combo.recovery(&HERO_AIR_ATTACK, true), 45);
+        // Nothing follows the final step until fully recovered, which starts the string over.
+        assert_eq!(combo.next(1), None);
+        assert_eq!(combo.next(0), Some(&HERO_UP_ATTACK));
+    }
+}
@@ -0,0 +1,159 @@
+use macroquad::*;
+use crate::input::{Action, KeyBindings, ACTIONS};
+
+const MENU_TEXT_SIZE: f32 = 20.;
+const MENU_WIDTH: f32 = 320.;
+const MENU_MARGIN: f32 = 10.;
+const SELECTED_COLOR: Color = Color([255, 220, 80, 255]);
+
+// Every key the menu watches, both for navigating and as candidates for a new binding.
+const KEYS: [KeyCode; 54] = [
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H, KeyCode::I,
+    KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N, KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R,
+    KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+    KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::Space, KeyCode::Enter, KeyCode::Tab, KeyCode::Backspace, KeyCode::Escape,
+    KeyCode::Left, KeyCode::Right, KeyCode::Up, KeyCode::Down,
+    KeyCode::LeftShift, KeyCode::RightShift, KeyCode::LeftControl, KeyCode::RightControl, KeyCode::LeftAlt,
+    KeyCode::Comma, KeyCode::Period, KeyCode::Slash, KeyCode::F2,
+];
+const MENU_KEY: KeyCode = KeyCode::F2;
+const CANCEL_KEY: KeyCode = KeyCode::Escape;
+
+// F2 opens the list of actions. Up/Down pick one and Enter waits for the next key press to
+// bind to it, or Escape to give up.
+pub struct ControlsMenu {
+    pub open: bool,
+    selected: usize,
+    capturing: bool,
+    // Shown after a rebind moved a key that another action was already using.
+    pub warning: Option<String>,
+    // Watched keys that were down last frame, so only fresh presses count.
+    held: Vec<KeyCode>,
+}
+
+impl ControlsMenu {
+
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            selected: 0,
+            capturing: false,
+            warning: None,
+            held: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, bindings: &mut KeyBindings) {
+        let down: Vec<KeyCode> = KEYS.iter().copied().filter(|&key| is_key_down(key)).collect();
+        let pressed: Vec<KeyCode> = down.iter().copied().filter(|key| !self.held.contains(key)).collect();
+        self.held = down;
+        for key in pressed {
+            self.press(bindings, key);
+        }
+    }
+
+    // Reacts to one fresh key press.
+    pub fn press(&mut self, bindings: &mut KeyBindings, key: KeyCode) {
+        if self.capturing {
+            self.capturing = false;
+            if key != CANCEL_KEY && key != MENU_KEY {
+                self.rebind(bindings, ACTIONS[self.selected], key);
+            }
+            return;
+        }
+        if key == MENU_KEY {
+            self.open = !self.open;
+            return;
+        }
+        if !self.open {
+            return;
+        }
+        match key {
+            KeyCode::Up => self.selected = (self.selected + ACTIONS.len() - 1) % ACTIONS.len(),
+            KeyCode::Down => self.selected = (self.selected + 1) % ACTIONS.len(),
+            KeyCode::Enter => {
+                self.capturing = true;
+                self.warning = None;
+            }
+            CANCEL_KEY => self.open = false,
+            _ => {}
+        }
+    }
+
+    // Binds `key` to `action`, warning when another action was using it too. Both keep the key
+    // until the player rebinds one of them.
+    pub fn rebind(&mut self, bindings: &mut KeyBindings, action: Action, key: KeyCode) {
+        self.warning = bindings.conflict(action, key)
+            .map(|other| format!("{:?} is also bound to {}", key, other.name()));
+        bindings.bind(action, key);
+    }
+
+    pub fn draw(&self, bindings: &KeyBindings) {
+        if !self.open {
+            return;
+        }
+        let lines = ACTIONS.len() + 3;
+        let height = MENU_TEXT_SIZE * lines as f32 + MENU_MARGIN;
+        let x = screen_width() - MENU_WIDTH - MENU_MARGIN;
+        draw_rectangle(x, MENU_MARGIN, MENU_WIDTH, height, Color([0, 0, 0, 180]));
+        let x = x + MENU_MARGIN;
+        let mut y = MENU_MARGIN + MENU_TEXT_SIZE;
+        draw_text("controls", x, y, MENU_TEXT_SIZE, WHITE);
+        for (i, &action) in ACTIONS.iter().enumerate() {
+            y += MENU_TEXT_SIZE;
+            let key = if self.capturing && i == self.selected {
+                "press a key...".to_string()
+            } else {
+                format!("{:?}", bindings.key(action))
+            };
+            let color = if i == self.selected { SELECTED_COLOR } else { WHITE };
+            draw_text(&format!("{} {}", action.name(), key), x, y, MENU_TEXT_SIZE, color);
+        }
+        if let Some(warning) = &self.warning {
+            y += MENU_TEXT_SIZE * 2.;
+            draw_text(warning, x, y, MENU_TEXT_SIZE, RED);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebind_to_a_free_key() {
+        let mut menu = ControlsMenu::new();
+        let mut bindings = KeyBindings::default();
+        menu.rebind(&mut bindings, Action::Jump, KeyCode::J);
+        assert_eq!(bindings.key(Action::Jump), KeyCode::J);
+        assert_eq!(menu.warning, None);
+    }
+
+    #[test]
+    fn rebind_to_a_used_key_warns() {
+        let mut menu = ControlsMenu::new();
+        let mut bindings = KeyBindings::default();
+        menu.rebind(&mut bindings, Action::Jump, KeyCode::Z);
+        assert_eq!(bindings.key(Action::Jump), KeyCode::Z);
+        assert_eq!(bindings.key(Action::Attack), KeyCode::Z);
+        assert_eq!(menu.warning.as_deref(), Some("Z is also bound to attack"));
+        menu.rebind(&mut bindings, Action::Jump, KeyCode::P);
+        assert_eq!(menu.warning.as_deref(), Some("P is also bound to pause"));
+    }
+
+    #[test]
+    fn capturing_binds_the_next_key() {
+        let mut menu = ControlsMenu::new();
+        let mut bindings = KeyBindings::default();
+        menu.press(&mut bindings, MENU_KEY);
+        assert!(menu.open);
+        menu.press(&mut bindings, KeyCode::Enter);
+        menu.press(&mut bindings, KeyCode::J);
+        assert_eq!(bindings.key(ACTIONS[0]), KeyCode::J);
+        menu.press(&mut bindings, KeyCode::Enter);
+        menu.press(&mut bindings, CANCEL_KEY);
+        assert_eq!(bindings.key(ACTIONS[0]), KeyCode::J);
+    }
+}
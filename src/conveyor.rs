@@ -0,0 +1,97 @@
+use nalgebra as na;
+use nphysics2d::material::BasicMaterial;
+use nphysics2d::object::{DefaultBodyHandle, DefaultBodySet, DefaultColliderSet};
+use nphysics2d::world::DefaultGeometricalWorld;
+use crate::GameObject;
+use crate::physics::is_standing_on;
+
+pub struct Conveyor {
+    pub object: GameObject,
+    // Horizontal belt speed, positive moves riders to the right.
+    pub speed: f32,
+    // Riders given the belt speed last tick.
+    carried: Vec<DefaultBodyHandle>,
+}
+
+impl Conveyor {
+
+    pub fn new(x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, width: f32, height: f32, speed: f32) -> Self {
+        let object = GameObject::new(x, y, bodies, colliders, width, height, 0., 0.);
+        // The surface moves with the belt, so friction only slows a rider's own movement and not
+        // the belt speed it's given.
+        let material = colliders.get_mut(object.collider_handle)
+            .and_then(|collider| collider.material_mut().downcast_mut::<BasicMaterial<f32>>());
+        if let Some(material) = material {
+            material.surface_velocity = Some(na::Vector2::new(speed, 0.));
+        }
+        Self {
+            object,
+            speed,
+            carried: Vec::new(),
+        }
+    }
+
+    // The belt speed is added on top of the velocity of whatever stands on it rather than
+    // overriding it, so a rider can still walk against the belt if its own speed is higher. Last
+    // tick's boost is taken back off first, so it doesn't build up.
+    pub fn carry(&mut self, geometrical_world: &DefaultGeometricalWorld<f32>, bodies: &mut DefaultBodySet<f32>, colliders: &DefaultColliderSet<f32>, riders: &[&GameObject]) {
+        let boost = na::Vector2::new(self.speed, 0.);
+        for handle in self.carried.drain(..) {
+            if let Some(body) = bodies.rigid_body_mut(handle) {
+                body.set_linear_velocity(body.velocity().linear - boost);
+            }
+        }
+        for rider in riders {
+            if !is_standing_on(geometrical_world, colliders, rider.collider_handle, self.object.collider_handle) {
+                continue;
+            }
+            if let Some(body) = bodies.rigid_body_mut(rider.body_handle) {
+                body.set_linear_velocity(body.velocity().linear + boost);
+                self.carried.push(rider.body_handle);
+            }
+        }
+    }
+
+    // Forgets last tick's boost on `body`, for when its velocity has been set outright rather than
+    // added to, so there's nothing to take back off.
+    pub fn release(&mut self, body: DefaultBodyHandle) {
+        self.carried.retain(|handle| *handle != body);
+    }
+
+    pub fn debug_draw(&self, bodies: &DefaultBodySet<f32>) {
+        self.object.debug_draw(bodies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::TestWorld;
+
+    #[test]
+    fn rider_drifts_with_the_belt() {
+        let mut world = TestWorld::new();
+        let mut conveyor = Conveyor::new(0., 100., &mut world.bodies, &mut world.colliders, 200., 5., 40.);
+        let rider = world.dynamic(0., 85., 10., 10.);
+        for _ in 0..60 {
+            world.step();
+            conveyor.carry(&world.geometrical_world, &mut world.bodies, &world.colliders, &[&rider]);
+        }
+        // A second on the belt, minus the first ticks before the contact is found.
+        let x = rider.position(&world.bodies).x;
+        assert!(x > 35. && x <= 40., "rider at {}", x);
+    }
+
+    #[test]
+    fn boost_does_not_build_up() {
+        let mut world = TestWorld::new();
+        let mut conveyor = Conveyor::new(0., 100., &mut world.bodies, &mut world.colliders, 200., 5., 40.);
+        let rider = world.dynamic(0., 85., 10., 10.);
+        for _ in 0..120 {
+            world.step();
+            conveyor.carry(&world.geometrical_world, &mut world.bodies, &world.colliders, &[&rider]);
+        }
+        let velocity = rider.linear_velocity(&world.bodies).x;
+        assert!((velocity - 40.).abs() < 1., "rider moving at {}", velocity);
+    }
+}
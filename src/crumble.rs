@@ -0,0 +1,157 @@
+use macroquad::*;
+use nalgebra as na;
+use nphysics2d::object::{DefaultBodySet, DefaultColliderSet};
+use nphysics2d::world::DefaultGeometricalWorld;
+use crate::GameObject;
+use crate::physics::{is_standing_on, set_passable};
+
+// Long enough for the hero to see the shake and hop off.
+const SHAKE_TIME: f32 = 0.6;
+const SHAKE_AMPLITUDE: f32 = 1.5;
+const SHAKE_FREQUENCY: f32 = 60.;
+const FALL_TIME: f32 = 1.;
+const FALL_ACCELERATION: f32 = 600.;
+const RESPAWN_TIME: f32 = 3.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CrumbleState {
+    Idle,
+    // Stepped on, still solid.
+    Shaking,
+    // Dropping away and no longer solid.
+    Falling,
+    // Gone until it reappears where it started.
+    Respawning,
+}
+
+pub struct CrumblePlatform {
+    pub object: GameObject,
+    pub state: CrumbleState,
+    // Time spent in the current state.
+    timer: f32,
+    spawn: na::Vector2<f32>,
+    fall_speed: f32,
+}
+
+impl CrumblePlatform {
+
+    pub fn new(x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, width: f32, height: f32) -> Self {
+        Self {
+            object: GameObject::new(x, y, bodies, colliders, width, height, 0., 0.),
+            state: CrumbleState::Idle,
+            timer: 0.,
+            spawn: na::Vector2::new(x, y),
+            fall_speed: 0.,
+        }
+    }
+
+    // Once stepped on the platform goes through every state on its own timer, whether or not
+    // anything is still standing on it.
+    pub fn update(&mut self, delta: f32, geometrical_world: &DefaultGeometricalWorld<f32>, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, riders: &[&GameObject]) {
+        self.timer += delta;
+        match self.state {
+            CrumbleState::Idle => {
+                if riders.iter().any(|rider| is_standing_on(geometrical_world, colliders, rider.collider_handle, self.object.collider_handle)) {
+                    self.enter(CrumbleState::Shaking);
+                }
+            }
+            CrumbleState::Shaking => {
+                if self.timer >= SHAKE_TIME {
+                    set_passable(colliders, self.object.collider_handle, true);
+                    // Whatever is still on it would otherwise sleep in mid air.
+                    for rider in riders {
+                        rider.rigid_body_mut(bodies).activate();
+                    }
+                    self.fall_speed = 0.;
+                    self.enter(CrumbleState::Falling);
+                }
+            }
+            CrumbleState::Falling => {
+                self.fall_speed += FALL_ACCELERATION * delta;
+                self.move_to(bodies, self.object.position(bodies) + na::Vector2::new(0., self.fall_speed * delta));
+                if self.timer >= FALL_TIME {
+                    self.move_to(bodies, self.spawn);
+                    self.enter(CrumbleState::Respawning);
+                }
+            }
+            CrumbleState::Respawning => {
+                if self.timer >= RESPAWN_TIME {
+                    set_passable(colliders, self.object.collider_handle, false);
+                    self.enter(CrumbleState::Idle);
+                }
+            }
+        }
+    }
+
+    fn enter(&mut self, state: CrumbleState) {
+        self.state = state;
+        self.timer = 0.;
+    }
+
+    fn move_to(&self, bodies: &mut DefaultBodySet<f32>, position: na::Vector2<f32>) {
+        if let Some(body) = bodies.rigid_body_mut(self.object.body_handle) {
+            let mut isometry = *body.position();
+            isometry.translation.vector = position;
+            body.set_position(isometry);
+        }
+    }
+
+    pub fn draw(&self, bodies: &DefaultBodySet<f32>) {
+        if self.state == CrumbleState::Respawning {
+            return;
+        }
+        let pos = self.object.position(bodies);
+        let shake = if self.state == CrumbleState::Shaking { (self.timer * SHAKE_FREQUENCY).sin() * SHAKE_AMPLITUDE } else { 0. };
+        draw_rectangle(pos.x - self.object.width + shake, pos.y - self.object.height, self.object.width, self.object.height, BROWN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::{TestWorld, DT};
+
+    fn is_solid(world: &TestWorld, platform: &CrumblePlatform) -> bool {
+        !world.colliders.get(platform.object.collider_handle).unwrap().is_sensor()
+    }
+
+    #[test]
+    fn stepped_on_platform_shakes_then_falls_and_comes_back() {
+        let mut world = TestWorld::new();
+        let mut platform = CrumblePlatform::new(0., 40., &mut world.bodies, &mut world.colliders, 30., 5.);
+        let hero = world.dynamic(0., 23., 10., 10.);
+        let step = |world: &mut TestWorld, platform: &mut CrumblePlatform| {
+            world.step();
+            platform.update(DT, &world.geometrical_world, &mut world.bodies, &mut world.colliders, &[&hero]);
+        };
+        for _ in 0..60 {
+            if platform.state != CrumbleState::Idle {
+                break;
+            }
+            step(&mut world, &mut platform);
+        }
+        assert_eq!(platform.state, CrumbleState::Shaking);
+        // Still solid while shaking, so there's time to hop off.
+        for _ in 0..(SHAKE_TIME / DT) as usize - 2 {
+            step(&mut world, &mut platform);
+        }
+        assert_eq!(platform.state, CrumbleState::Shaking);
+        assert!(is_solid(&world, &platform));
+        for _ in 0..3 {
+            step(&mut world, &mut platform);
+        }
+        assert_eq!(platform.state, CrumbleState::Falling);
+        assert!(!is_solid(&world, &platform));
+        for _ in 0..(FALL_TIME / DT) as usize / 2 {
+            step(&mut world, &mut platform);
+        }
+        assert!(platform.object.position(&world.bodies).y > 40.);
+
+        for _ in 0..((FALL_TIME + RESPAWN_TIME) / DT) as usize + 2 {
+            step(&mut world, &mut platform);
+        }
+        assert_eq!(platform.state, CrumbleState::Idle);
+        assert!(is_solid(&world, &platform));
+        assert!((platform.object.position(&world.bodies) - na::Vector2::new(0., 40.)).norm() < 1e-3);
+    }
+}
@@ -0,0 +1,150 @@
+use macroquad::*;
+
+const MENU_TEXT_SIZE: f32 = 20.;
+const MENU_WIDTH: f32 = 220.;
+const MENU_MARGIN: f32 = 10.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugOverlay {
+    Fps,
+    // Bounding box of every collider.
+    Colliders,
+    // The last attack's hitbox.
+    Hitboxes,
+    // Tick and time readout.
+    Timing,
+}
+
+// Listed in menu order, the first one toggled by 1, the next by 2 and so on.
+pub const DEBUG_OVERLAYS: [DebugOverlay; 4] = [DebugOverlay::Fps, DebugOverlay::Colliders, DebugOverlay::Hitboxes, DebugOverlay::Timing];
+
+impl DebugOverlay {
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugOverlay::Fps => "fps",
+            DebugOverlay::Colliders => "colliders",
+            DebugOverlay::Hitboxes => "hitboxes",
+            DebugOverlay::Timing => "timing",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DebugOverlays {
+    pub fps: bool,
+    pub colliders: bool,
+    pub hitboxes: bool,
+    pub timing: bool,
+}
+
+// Debug builds start with the overlays that used to be always on there.
+impl Default for DebugOverlays {
+    fn default() -> Self {
+        Self {
+            fps: false,
+            colliders: false,
+            hitboxes: cfg!(debug_assertions),
+            timing: cfg!(debug_assertions),
+        }
+    }
+}
+
+impl DebugOverlays {
+
+    pub fn is_on(&self, overlay: DebugOverlay) -> bool {
+        match overlay {
+            DebugOverlay::Fps => self.fps,
+            DebugOverlay::Colliders => self.colliders,
+            DebugOverlay::Hitboxes => self.hitboxes,
+            DebugOverlay::Timing => self.timing,
+        }
+    }
+
+    pub fn toggle(&mut self, overlay: DebugOverlay) {
+        let flag = match overlay {
+            DebugOverlay::Fps => &mut self.fps,
+            DebugOverlay::Colliders => &mut self.colliders,
+            DebugOverlay::Hitboxes => &mut self.hitboxes,
+            DebugOverlay::Timing => &mut self.timing,
+        };
+        *flag = !*flag;
+    }
+}
+
+// Lists every overlay with its state. Only available in debug builds.
+pub struct DebugMenu {
+    pub open: bool,
+    pub overlays: DebugOverlays,
+    // Key states from last frame, so holding a key doesn't keep toggling.
+    menu_key_held: bool,
+    toggle_keys_held: [bool; DEBUG_OVERLAYS.len()],
+}
+
+impl DebugMenu {
+
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            overlays: DebugOverlays::default(),
+            menu_key_held: false,
+            toggle_keys_held: [false; DEBUG_OVERLAYS.len()],
+        }
+    }
+
+    // `toggle_keys[i]` flips `DEBUG_OVERLAYS[i]`, but only while the menu is open.
+    pub fn update(&mut self, menu_key: bool, toggle_keys: [bool; DEBUG_OVERLAYS.len()]) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        if menu_key && !self.menu_key_held {
+            self.open = !self.open;
+        }
+        self.menu_key_held = menu_key;
+        for (i, &overlay) in DEBUG_OVERLAYS.iter().enumerate() {
+            if self.open && toggle_keys[i] && !self.toggle_keys_held[i] {
+                self.overlays.toggle(overlay);
+            }
+        }
+        self.toggle_keys_held = toggle_keys;
+    }
+
+    pub fn draw(&self) {
+        if !self.open {
+            return;
+        }
+        let height = MENU_TEXT_SIZE * (DEBUG_OVERLAYS.len() + 1) as f32 + MENU_MARGIN;
+        draw_rectangle(MENU_MARGIN, MENU_MARGIN, MENU_WIDTH, height, Color([0, 0, 0, 180]));
+        let x = MENU_MARGIN * 2.;
+        let mut y = MENU_MARGIN + MENU_TEXT_SIZE;
+        draw_text("debug overlays", x, y, MENU_TEXT_SIZE, WHITE);
+        for (i, &overlay) in DEBUG_OVERLAYS.iter().enumerate() {
+            y += MENU_TEXT_SIZE;
+            let state = if self.overlays.is_on(overlay) { "on" } else { "off" };
+            draw_text(&format!("{} {} {}", i + 1, overlay.name(), state), x, y, MENU_TEXT_SIZE, WHITE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_toggle_flips_only_that_overlay() {
+        let mut menu = DebugMenu::new();
+        let before = menu.overlays;
+        let colliders = [false, true, false, false];
+        // Ignored while the menu is closed.
+        menu.update(false, colliders);
+        assert_eq!(menu.overlays, before);
+
+        menu.update(true, [false; DEBUG_OVERLAYS.len()]);
+        assert!(menu.open);
+        menu.update(false, colliders);
+        assert_eq!(menu.overlays, DebugOverlays { colliders: !before.colliders, ..before });
+        // Holding the key doesn't keep flipping it.
+        menu.update(false, colliders);
+        assert_eq!(menu.overlays, DebugOverlays { colliders: !before.colliders, ..before });
+    }
+}
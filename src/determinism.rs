@@ -0,0 +1,168 @@
+use crate::input::InputState;
+use crate::settings::UserSettings;
+use crate::world::GameWorld;
+
+// Debug aid that steps a second, shadow world alongside the real one. Both start from the same
+// state and get the same input every tick, so as long as the logic is deterministic they stay
+// identical; the first tick they differ is reported. Running every tick twice is slow, so it's
+// opt-in even in debug builds.
+//
+// What a tick depends on, and why it repeats:
+// - Bodies, colliders and joints live in generational arenas, which nphysics and the game both
+//   iterate in slot order. Slots only depend on the order things were inserted and removed in,
+//   so the same level and inputs give the same order.
+// - Maps the game keeps by handle (collision tags, impact velocities) are ordered maps, never
+//   hash maps, so looping over them can't depend on a per-process hash seed.
+// - Time only enters through the fixed `delta` handed to `GameWorld::update`; wall-clock time is
+//   only read for the HUD.
+// - Gameplay rolls come from the world's own `Rng`, seeded from the level.
+// - Particles draw from macroquad's global random generator, which nothing else shares. They are
+//   purely visual and feed nothing back into the simulation, so they are left out of the check.
+pub struct DeterminismCheck {
+    shadow: GameWorld,
+    diverged: bool,
+}
+
+impl DeterminismCheck {
+
+    // `shadow` has to be built exactly like the world it checks. Its side effects are turned off.
+    pub fn new(mut shadow: GameWorld) -> Self {
+        shadow.side_effects = false;
+        Self {
+            shadow,
+            diverged: false,
+        }
+    }
+
+    // Settings changed mid-game have to reach both worlds.
+    pub fn apply_settings(&mut self, settings: &UserSettings) {
+        self.shadow.apply_settings(settings);
+    }
+
+    // Steps the shadow world with the input `world` was just stepped with, then compares them.
+    // Returns false once they have diverged; the check stays quiet after the first report.
+    pub fn step(&mut self, world: &GameWorld, delta: f32, input: &InputState) -> bool {
+        if self.diverged {
+            return false;
+        }
+        // The view follows the window, which only the game loop sees, and mouse input goes
+        // through it.
+        self.shadow.camera.screen = world.camera.screen;
+        self.shadow.camera.render_scale = world.camera.render_scale;
+        self.shadow.update(delta, input);
+        self.shadow.audio.drain();
+        if state(world) != state(&self.shadow) {
+            eprintln!("Warning: nondeterministic update, the worlds diverged at tick {}", world.tick);
+            self.diverged = true;
+        }
+        !self.diverged
+    }
+}
+
+// Everything a tick is expected to reproduce exactly: every body's position and velocity plus
+// the gameplay counters that don't live in the physics world.
+fn state(world: &GameWorld) -> Vec<f32> {
+    let mut state = vec![world.tick as f32, world.hero.health as f32, world.coins_collected as f32, world.enemies.len() as f32];
+    state.extend(world.enemies.iter().map(|enemy| enemy.health as f32));
+    for (handle, body) in world.bodies.iter() {
+        let rigid_body = match world.bodies.rigid_body(handle) {
+            Some(rigid_body) => rigid_body,
+            None => continue,
+        };
+        let position = rigid_body.position();
+        let velocity = rigid_body.velocity();
+        state.extend_from_slice(&[
+            position.translation.vector.x,
+            position.translation.vector.y,
+            position.rotation.angle(),
+            velocity.linear.x,
+            velocity.linear.y,
+            velocity.angular,
+        ]);
+        state.push(if body.is_active() { 1. } else { 0. });
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra as na;
+    use crate::profile::Profile;
+
+    const DT: f32 = 1. / 60.;
+
+    // Runs right, jumps, swings and runs back, one input per tick.
+    fn scripted_inputs() -> Vec<InputState> {
+        (0..240).map(|tick| InputState {
+            right: tick < 120,
+            left: tick >= 160,
+            jump: (30..34).contains(&tick) || (170..174).contains(&tick),
+            attack: (60..62).contains(&tick),
+            ..InputState::default()
+        }).collect()
+    }
+
+    fn run(inputs: &[InputState]) -> GameWorld {
+        let mut world = new_world();
+        for input in inputs {
+            world.update(DT, input);
+        }
+        world
+    }
+
+    fn new_world() -> GameWorld {
+        GameWorld::new(&UserSettings::default(), Profile::default())
+    }
+
+    #[test]
+    fn check_passes_for_deterministic_updates() {
+        let mut world = new_world();
+        let mut check = DeterminismCheck::new(new_world());
+        for input in &scripted_inputs() {
+            world.update(DT, input);
+            assert!(check.step(&world, DT, input));
+        }
+    }
+
+    // Stands in for a nondeterministic system by nudging a body in the real world only.
+    #[test]
+    fn check_detects_divergence() {
+        let mut world = new_world();
+        let mut check = DeterminismCheck::new(new_world());
+        let inputs = scripted_inputs();
+        for input in &inputs[..10] {
+            world.update(DT, input);
+            assert!(check.step(&world, DT, input));
+        }
+        let velocity = world.block.linear_velocity(&world.bodies);
+        world.block.set_linear_velocity(&mut world.bodies, velocity + na::Vector2::new(1., 0.));
+        world.update(DT, &inputs[10]);
+        assert!(!check.step(&world, DT, &inputs[10]));
+        // Quiet after the first report.
+        world.update(DT, &inputs[11]);
+        assert!(!check.step(&world, DT, &inputs[11]));
+    }
+
+    #[test]
+    fn shadow_world_stays_without_side_effects_across_restarts() {
+        let mut world = new_world();
+        let mut check = DeterminismCheck::new(new_world());
+        let restart = InputState { restart: true, ..InputState::default() };
+        world.update(DT, &restart);
+        assert!(check.step(&world, DT, &restart));
+        assert!(world.side_effects);
+        assert!(!check.shadow.side_effects);
+    }
+
+    #[test]
+    fn same_inputs_give_the_same_hero() {
+        let inputs = scripted_inputs();
+        let (first, second) = (run(&inputs), run(&inputs));
+        assert_ne!(first.hero.object.position(&first.bodies), first.hero_start);
+        assert_eq!(first.hero.object.position(&first.bodies), second.hero.object.position(&second.bodies));
+        assert_eq!(first.hero.object.linear_velocity(&first.bodies), second.hero.object.linear_velocity(&second.bodies));
+        assert_eq!(first.hero.health, second.hero.health);
+        assert_eq!(state(&first), state(&second));
+    }
+}
@@ -0,0 +1,513 @@
+use macroquad::*;
+use nalgebra as na;
+use nphysics2d::object::{DefaultBodySet, DefaultColliderSet};
+use crate::GameObject;
+use crate::ai::{AiStack, AiState};
+use crate::physics::set_corpse;
+use crate::projectile::Projectile;
+use crate::settings::{scale_stat, Difficulty};
+
+const CONTACT_DAMAGE: u32 = 1;
+const PATROL_RANGE: f32 = 50.;
+const PATROL_SPEED: f32 = 20.;
+const CHASE_SPEED: f32 = 35.;
+const CHARGE_SPEED: f32 = 80.;
+const DETECT_RADIUS: f32 = 120.;
+const LOSE_RADIUS: f32 = 200.;
+const RANGED_COOLDOWN: f32 = 1.5;
+const PROJECTILE_SPEED: f32 = 100.;
+const PROJECTILE_DAMAGE: u32 = 1;
+// Seconds a boss stays invulnerable while switching phases.
+const PHASE_TRANSITION: f32 = 1.;
+const FLASH_INTERVAL: f32 = 0.1;
+// Seconds the "!" stays above an enemy that just spotted the hero.
+const ALERT_DURATION: f32 = 0.8;
+// Upward share of a knockback, relative to its horizontal speed.
+const KNOCKBACK_LIFT: f32 = 0.4;
+// Seconds a knocked back enemy drifts before its AI steers again.
+const KNOCKBACK_STAGGER: f32 = 0.25;
+const BOSS_KNOCKBACK_RESISTANCE: f32 = 0.8;
+const AGGRO_RADIUS: f32 = 100.;
+const DEATH_ANIMATION_TIME: f32 = 0.5;
+// Seconds the outline flashes after blocking a hit.
+const BLOCK_FLASH: f32 = 0.2;
+pub const RAGDOLL_FADE_TIME: f32 = 1.5;
+// Upward kick and spin a ragdoll gets as it dies.
+const RAGDOLL_POP: f32 = 60.;
+const RAGDOLL_SPIN: f32 = 6.;
+// A thrown enemy slower than this has landed and can't hurt anyone any more.
+const THROWN_SETTLE_SPEED: f32 = 20.;
+
+pub struct BossPhase {
+    // The phase starts once the boss health drops to this value.
+    pub health: u32,
+    pub behavior: AiState,
+}
+
+pub struct Enemy {
+    pub object: GameObject,
+    pub health: u32,
+    pub ai: AiStack,
+    pub phases: Vec<BossPhase>,
+    pub phase: usize,
+    pub invulnerable: f32,
+    pub alert: f32,
+    pub alert_duration: f32,
+    // Share of incoming knockback ignored, from 0 (full push) to 1 (immovable).
+    pub knockback_resistance: f32,
+    // Patrols also turn around at the edge of a platform instead of walking off it.
+    pub turns_at_ledges: bool,
+    // Getting hit alerts other enemies within this distance, none when zero.
+    pub aggro_radius: f32,
+    // Damage dealt by touching the hero and by each shot.
+    pub contact_damage: u32,
+    pub projectile_damage: u32,
+    // Chance, 0 to 1, of blocking each of the hero's melee hits.
+    pub block_chance: f32,
+    // Held above the hero's head, and flying after being thrown until it lands.
+    pub carried: bool,
+    pub thrown: bool,
+    facing: f32,
+    fire_cooldown: f32,
+    stagger: f32,
+    stun: f32,
+    block_flash: f32,
+}
+
+impl Enemy {
+
+    pub fn new(x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, health: u32) -> Self {
+        let patrol = AiState::Patrol { left: x - PATROL_RANGE, right: x + PATROL_RANGE };
+        Self {
+            object: GameObject::new(x, y, bodies, colliders, 10., 10., 75., 1.),
+            health,
+            ai: AiStack::new(patrol),
+            phases: Vec::new(),
+            phase: 0,
+            invulnerable: 0.,
+            alert: 0.,
+            alert_duration: ALERT_DURATION,
+            knockback_resistance: 0.,
+            turns_at_ledges: true,
+            aggro_radius: AGGRO_RADIUS,
+            contact_damage: CONTACT_DAMAGE,
+            projectile_damage: PROJECTILE_DAMAGE,
+            block_chance: 0.,
+            carried: false,
+            thrown: false,
+            facing: 1.,
+            fire_cooldown: 0.,
+            stagger: 0.,
+            stun: 0.,
+            block_flash: 0.,
+        }
+    }
+
+    pub fn boss(x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, phases: Vec<BossPhase>) -> Self {
+        Self {
+            object: GameObject::new(x, y, bodies, colliders, 20., 20., 300., 1.),
+            health: phases[0].health,
+            ai: AiStack::new(phases[0].behavior),
+            phases,
+            phase: 0,
+            invulnerable: 0.,
+            alert: 0.,
+            alert_duration: ALERT_DURATION,
+            knockback_resistance: BOSS_KNOCKBACK_RESISTANCE,
+            turns_at_ledges: false,
+            aggro_radius: 0.,
+            contact_damage: CONTACT_DAMAGE,
+            projectile_damage: PROJECTILE_DAMAGE,
+            block_chance: 0.,
+            carried: false,
+            thrown: false,
+            facing: -1.,
+            fire_cooldown: 0.,
+            stagger: 0.,
+            stun: 0.,
+            block_flash: 0.,
+        }
+    }
+
+    // Scales health, boss phase thresholds included, and damage from the base values.
+    pub fn scaled(mut self, difficulty: Difficulty) -> Self {
+        self.health = scale_stat(self.health, difficulty.health_multiplier());
+        for phase in &mut self.phases {
+            phase.health = scale_stat(phase.health, difficulty.health_multiplier());
+        }
+        self.contact_damage = scale_stat(self.contact_damage, difficulty.damage_multiplier());
+        self.projectile_damage = scale_stat(self.projectile_damage, difficulty.damage_multiplier());
+        self
+    }
+
+    pub fn with_block_chance(mut self, chance: f32) -> Self {
+        self.block_chance = chance.clamp(0., 1.);
+        self
+    }
+
+    // Shows that a hit was just blocked.
+    pub fn block(&mut self) {
+        self.block_flash = BLOCK_FLASH;
+    }
+
+    pub fn is_boss(&self) -> bool {
+        !self.phases.is_empty()
+    }
+
+    // Returns the damage actually taken, which is zero while invulnerable.
+    pub fn hit(&mut self, damage: u32) -> u32 {
+        if self.invulnerable > 0. {
+            return 0;
+        }
+        let health = self.health;
+        self.health = self.health.saturating_sub(damage);
+        if self.is_dead() {
+            return health;
+        }
+        let phase = self.phase;
+        while self.phase + 1 < self.phases.len() && self.health <= self.phases[self.phase + 1].health {
+            self.phase += 1;
+        }
+        if self.phase != phase {
+            self.ai.set_base(self.phases[self.phase].behavior);
+            self.invulnerable = PHASE_TRANSITION;
+        }
+        health - self.health
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health == 0
+    }
+
+    pub fn is_patrolling(&self) -> bool {
+        matches!(self.ai.current(), AiState::Patrol { .. })
+    }
+
+    // Switches a patrolling enemy over to chasing the hero. Bosses keep to their phases.
+    pub fn alert(&mut self) {
+        if !self.is_boss() && self.is_patrolling() {
+            self.ai.push(AiState::Chase);
+            self.alert = self.alert_duration;
+        }
+    }
+
+    // -1 left, 1 right.
+    pub fn facing(&self) -> f32 {
+        self.facing
+    }
+
+    pub fn turn_around(&mut self) {
+        self.facing = -self.facing;
+    }
+
+    // Pushes the enemy toward `direction` (1 right, -1 left), scaled down by its resistance.
+    pub fn knock_back(&mut self, bodies: &mut DefaultBodySet<f32>, direction: f32, speed: f32) {
+        let speed = speed * (1. - self.knockback_resistance.clamp(0., 1.));
+        if speed <= 0. {
+            return;
+        }
+        self.object.set_linear_velocity(bodies, na::Vector2::new(direction * speed, -speed * KNOCKBACK_LIFT));
+        self.stagger = KNOCKBACK_STAGGER;
+    }
+
+    // Stops the AI for `duration` seconds, during which the enemy can be picked up. Bosses shrug
+    // it off.
+    pub fn stun(&mut self, duration: f32) {
+        if !self.is_boss() {
+            self.stun = self.stun.max(duration);
+        }
+    }
+
+    // Being carried or thrown keeps it stunned however long that takes.
+    pub fn is_stunned(&self) -> bool {
+        self.stun > 0. || self.carried || self.thrown
+    }
+
+    pub fn think(&mut self, delta: f32, hero: na::Vector2<f32>, bodies: &mut DefaultBodySet<f32>, projectiles: &mut Vec<Projectile>) {
+        self.invulnerable = (self.invulnerable - delta).max(0.);
+        self.fire_cooldown = (self.fire_cooldown - delta).max(0.);
+        self.alert = (self.alert - delta).max(0.);
+        self.stagger = (self.stagger - delta).max(0.);
+        self.block_flash = (self.block_flash - delta).max(0.);
+        if self.thrown && self.object.linear_velocity(bodies).norm() < THROWN_SETTLE_SPEED {
+            self.thrown = false;
+        }
+        if self.carried || self.thrown {
+            return;
+        }
+        if self.stun > 0. {
+            self.stun = (self.stun - delta).max(0.);
+            return;
+        }
+        let position = self.object.position(bodies);
+        let to_hero = hero - position;
+        let speed = if self.is_boss() && self.invulnerable > 0. {
+            0.
+        } else {
+            match self.ai.current() {
+                AiState::Patrol { left, right } => {
+                    if position.x <= left {
+                        self.facing = 1.;
+                    } else if position.x >= right {
+                        self.facing = -1.;
+                    }
+                    if to_hero.norm() < DETECT_RADIUS {
+                        self.alert();
+                    }
+                    self.facing * PATROL_SPEED
+                }
+                AiState::Chase => {
+                    if to_hero.norm() > LOSE_RADIUS {
+                        self.ai.pop();
+                    }
+                    self.facing = to_hero.x.signum();
+                    self.facing * CHASE_SPEED
+                }
+                AiState::Charge => {
+                    self.facing = to_hero.x.signum();
+                    self.facing * CHARGE_SPEED
+                }
+                AiState::Ranged => {
+                    self.facing = to_hero.x.signum();
+                    if self.fire_cooldown <= 0. {
+                        let velocity = to_hero.try_normalize(0.).unwrap_or_else(na::Vector2::x) * PROJECTILE_SPEED;
+                        projectiles.push(Projectile::new(position, velocity, self.projectile_damage));
+                        self.fire_cooldown = RANGED_COOLDOWN;
+                    }
+                    0.
+                }
+            }
+        };
+        if self.stagger > 0. {
+            return;
+        }
+        let velocity = self.object.linear_velocity(bodies);
+        self.object.set_linear_velocity(bodies, na::Vector2::new(speed, velocity.y));
+    }
+
+    pub fn despawn(self, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>) {
+        colliders.remove(self.object.collider_handle);
+        bodies.remove(self.object.body_handle);
+    }
+
+    // Takes the enemy out of the fight right away and leaves behind its death animation. Without
+    // `ragdoll` the body goes too and the animation touches nothing; with it the body stays as a
+    // tumbling corpse that only collides with the level.
+    pub fn die(self, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, ragdoll: bool) -> EnemyCorpse {
+        let mut corpse = EnemyCorpse {
+            position: self.object.position(bodies),
+            width: self.object.width,
+            height: self.object.height,
+            timer: DEATH_ANIMATION_TIME,
+            duration: DEATH_ANIMATION_TIME,
+            ragdoll: None,
+        };
+        if !ragdoll {
+            self.despawn(bodies, colliders);
+            return corpse;
+        }
+        set_corpse(colliders, self.object.collider_handle);
+        if let Some(body) = bodies.rigid_body_mut(self.object.body_handle) {
+            let velocity = body.velocity().linear;
+            body.set_linear_velocity(na::Vector2::new(velocity.x, velocity.y - RAGDOLL_POP));
+            body.set_angular_velocity(RAGDOLL_SPIN * self.facing);
+        }
+        corpse.timer = RAGDOLL_FADE_TIME;
+        corpse.duration = RAGDOLL_FADE_TIME;
+        corpse.ragdoll = Some(self.object);
+        corpse
+    }
+
+    pub fn debug_draw(&self, bodies: &DefaultBodySet<f32>) {
+        if self.invulnerable <= 0. || (self.invulnerable / FLASH_INTERVAL) as u32 & 1 == 0 {
+            self.object.debug_draw(bodies);
+        }
+        if self.block_flash > 0. {
+            let position = self.object.position(bodies);
+            draw_rectangle_lines(position.x - self.object.width, position.y - self.object.height, self.object.width, self.object.height, 2., WHITE);
+        }
+        if self.alert > 0. {
+            let position = self.object.position(bodies);
+            draw_text("!", position.x - 3., position.y - self.object.height - 20., 24., RED);
+        } else if self.is_stunned() {
+            let position = self.object.position(bodies);
+            draw_text("*", position.x - 3., position.y - self.object.height - 20., 24., YELLOW);
+        }
+    }
+}
+
+// What is left of a dead enemy while its death animation plays.
+pub struct EnemyCorpse {
+    position: na::Vector2<f32>,
+    width: f32,
+    height: f32,
+    timer: f32,
+    duration: f32,
+    // The enemy's own body, still in the physics world, when it died as a ragdoll.
+    ragdoll: Option<GameObject>,
+}
+
+impl EnemyCorpse {
+
+    pub fn update(&mut self, delta: f32) {
+        self.timer -= delta;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.timer <= 0.
+    }
+
+    pub fn despawn(self, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>) {
+        if let Some(object) = self.ragdoll {
+            colliders.remove(object.collider_handle);
+            bodies.remove(object.body_handle);
+        }
+    }
+
+    // A ragdoll fades out as it tumbles, anything else squashes down into the floor while fading.
+    pub fn draw(&self, bodies: &DefaultBodySet<f32>) {
+        let progress = (self.timer / self.duration).clamp(0., 1.);
+        let color = Color([230, 41, 55, (255. * progress) as u8]);
+        let object = match &self.ragdoll {
+            Some(object) => object,
+            None => {
+                let height = self.height * progress;
+                let bottom = self.position.y;
+                draw_rectangle(self.position.x - self.width, bottom - height, self.width, height, color);
+                return;
+            }
+        };
+        let position = object.rigid_body(bodies).position();
+        let (half_width, half_height) = (self.width / 2., self.height / 2.);
+        let corners = [
+            na::Point2::new(-half_width, -half_height),
+            na::Point2::new(half_width, -half_height),
+            na::Point2::new(half_width, half_height),
+            na::Point2::new(-half_width, half_height),
+        ].map(|corner| position * corner);
+        for i in 0..corners.len() {
+            let (a, b) = (corners[i], corners[(i + 1) % corners.len()]);
+            draw_line(a.x, a.y, b.x, b.y, 2., color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attack::HERO_ATTACK;
+    use crate::physics::testing::{TestWorld, DT};
+
+    fn boss(world: &mut TestWorld) -> Enemy {
+        Enemy::boss(0., 0., &mut world.bodies, &mut world.colliders, vec![
+            BossPhase { health: 10, behavior: AiState::Patrol { left: -50., right: 50. } },
+            BossPhase { health: 6, behavior: AiState::Charge },
+            BossPhase { health: 3, behavior: AiState::Ranged },
+        ])
+    }
+
+    #[test]
+    fn alert_shows_for_the_configured_time_when_chasing() {
+        let mut world = TestWorld::new();
+        let mut enemy = Enemy::new(0., 0., &mut world.bodies, &mut world.colliders, 2);
+        enemy.alert_duration = 0.5;
+        let mut projectiles = Vec::new();
+        enemy.think(DT, na::Vector2::new(DETECT_RADIUS + 10., 0.), &mut world.bodies, &mut projectiles);
+        assert_eq!(enemy.alert, 0.);
+        enemy.think(DT, na::Vector2::new(DETECT_RADIUS - 10., 0.), &mut world.bodies, &mut projectiles);
+        assert_eq!(enemy.ai.current(), AiState::Chase);
+        assert_eq!(enemy.alert, 0.5);
+        for _ in 0..(0.5 / DT) as usize - 1 {
+            enemy.think(DT, na::Vector2::new(50., 0.), &mut world.bodies, &mut projectiles);
+        }
+        assert!(enemy.alert > 0.);
+        enemy.think(DT, na::Vector2::new(50., 0.), &mut world.bodies, &mut projectiles);
+        enemy.think(DT, na::Vector2::new(50., 0.), &mut world.bodies, &mut projectiles);
+        assert_eq!(enemy.alert, 0.);
+        assert_eq!(enemy.ai.current(), AiState::Chase);
+    }
+
+    #[test]
+    fn boss_changes_phase_past_the_threshold() {
+        let mut world = TestWorld::new();
+        let mut boss = boss(&mut world);
+        assert_eq!(boss.hit(3), 3);
+        assert_eq!(boss.phase, 0);
+        assert_eq!(boss.hit(1), 1);
+        assert_eq!(boss.phase, 1);
+        assert_eq!(boss.ai.current(), AiState::Charge);
+        // Invulnerable while changing phase, then charging at the hero.
+        assert_eq!(boss.hit(1), 0);
+        let mut projectiles = Vec::new();
+        boss.think(DT, na::Vector2::new(100., 0.), &mut world.bodies, &mut projectiles);
+        assert_eq!(boss.object.linear_velocity(&world.bodies).x, 0.);
+        for _ in 0..(PHASE_TRANSITION / DT) as usize + 1 {
+            boss.think(DT, na::Vector2::new(100., 0.), &mut world.bodies, &mut projectiles);
+        }
+        assert_eq!(boss.object.linear_velocity(&world.bodies).x, CHARGE_SPEED);
+        assert_eq!(boss.hit(1), 1);
+    }
+
+    #[test]
+    fn a_big_hit_skips_phases() {
+        let mut world = TestWorld::new();
+        let mut boss = boss(&mut world);
+        boss.hit(8);
+        assert_eq!(boss.phase, 2);
+        assert_eq!(boss.ai.current(), AiState::Ranged);
+    }
+
+    #[test]
+    fn resistant_enemies_are_knocked_back_less() {
+        let mut world = TestWorld::new();
+        let mut light = Enemy::new(0., 0., &mut world.bodies, &mut world.colliders, 2);
+        let mut heavy = Enemy::new(100., 0., &mut world.bodies, &mut world.colliders, 2);
+        heavy.knockback_resistance = 0.75;
+        light.knock_back(&mut world.bodies, 1., HERO_ATTACK.knockback);
+        heavy.knock_back(&mut world.bodies, 1., HERO_ATTACK.knockback);
+        let light_speed = light.object.linear_velocity(&world.bodies).x;
+        let heavy_speed = heavy.object.linear_velocity(&world.bodies).x;
+        assert_eq!(light_speed, HERO_ATTACK.knockback);
+        assert!((heavy_speed - light_speed / 4.).abs() < 1e-3, "knocked back at {}", heavy_speed);
+    }
+
+    #[test]
+    fn hard_enemies_have_proportionally_more_health() {
+        let mut world = TestWorld::new();
+        let easy = Enemy::new(0., 0., &mut world.bodies, &mut world.colliders, 4).scaled(Difficulty::Easy);
+        let hard = Enemy::new(100., 0., &mut world.bodies, &mut world.colliders, 4).scaled(Difficulty::Hard);
+        assert_eq!(easy.health, 2);
+        assert_eq!(hard.health, 6);
+        assert_eq!(hard.health as f32 / easy.health as f32, Difficulty::Hard.health_multiplier() / Difficulty::Easy.health_multiplier());
+        assert!(hard.contact_damage > easy.contact_damage);
+    }
+
+    #[test]
+    fn dead_enemy_loses_its_collider_at_once_but_its_animation_plays_out() {
+        let mut world = TestWorld::new();
+        let mut enemy = Enemy::new(0., 0., &mut world.bodies, &mut world.colliders, 1);
+        let handle = enemy.object.collider_handle;
+        enemy.hit(1);
+        assert!(enemy.is_dead());
+        let mut corpse = enemy.die(&mut world.bodies, &mut world.colliders, false);
+        assert!(world.colliders.get(handle).is_none());
+        for _ in 0..(DEATH_ANIMATION_TIME / DT) as usize - 1 {
+            corpse.update(DT);
+        }
+        assert!(!corpse.is_finished());
+        corpse.update(DT);
+        corpse.update(DT);
+        assert!(corpse.is_finished());
+    }
+
+    #[test]
+    fn ragdoll_keeps_its_collider_until_despawned() {
+        let mut world = TestWorld::new();
+        let enemy = Enemy::new(0., 0., &mut world.bodies, &mut world.colliders, 1);
+        let handle = enemy.object.collider_handle;
+        let corpse = enemy.die(&mut world.bodies, &mut world.colliders, true);
+        assert!(world.colliders.get(handle).is_some());
+        corpse.despawn(&mut world.bodies, &mut world.colliders);
+        assert!(world.colliders.get(handle).is_none());
+    }
+}
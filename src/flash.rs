@@ -0,0 +1,78 @@
+use macroquad::*;
+
+// Full-screen overlay that starts at `color` and fades out linearly, drawn over the world and
+// the HUD.
+pub struct ScreenFlash {
+    pub reduce_motion: bool,
+    color: Color,
+    duration: f32,
+    remaining: f32,
+}
+
+impl ScreenFlash {
+
+    pub fn new(reduce_motion: bool) -> Self {
+        Self {
+            reduce_motion,
+            color: WHITE,
+            duration: 0.,
+            remaining: 0.,
+        }
+    }
+
+    // A newer flash replaces whatever is still fading.
+    pub fn start(&mut self, color: Color, duration: f32) {
+        if self.reduce_motion || duration <= 0. {
+            return;
+        }
+        self.color = color;
+        self.duration = duration;
+        self.remaining = duration;
+    }
+
+    // Fades by real (unscaled) time, so slow motion doesn't drag the flash out.
+    pub fn update(&mut self, real_delta: f32) {
+        self.remaining = (self.remaining - real_delta).max(0.);
+    }
+
+    // Overlay opacity from the flash color's own alpha down to 0.
+    pub fn alpha(&self) -> u8 {
+        if self.remaining <= 0. {
+            return 0;
+        }
+        (self.color.0[3] as f32 * self.remaining / self.duration) as u8
+    }
+
+    pub fn draw(&self) {
+        let alpha = self.alpha();
+        if alpha > 0 {
+            let Color([r, g, b, _]) = self.color;
+            draw_rectangle(0., 0., screen_width(), screen_height(), Color([r, g, b, alpha]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flash_fades_out_over_its_duration() {
+        let mut flash = ScreenFlash::new(false);
+        flash.start(Color([255, 0, 0, 200]), 0.5);
+        assert_eq!(flash.alpha(), 200);
+        flash.update(0.25);
+        assert_eq!(flash.alpha(), 100);
+        flash.update(0.25);
+        assert_eq!(flash.alpha(), 0);
+        flash.update(0.25);
+        assert_eq!(flash.alpha(), 0);
+    }
+
+    #[test]
+    fn reduce_motion_skips_the_flash() {
+        let mut flash = ScreenFlash::new(true);
+        flash.start(WHITE, 0.5);
+        assert_eq!(flash.alpha(), 0);
+    }
+}
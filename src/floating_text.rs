@@ -0,0 +1,77 @@
+use macroquad::*;
+
+struct Label {
+    position: Vec2,
+    text: String,
+    color: Color,
+    life: f32,
+}
+
+// Short-lived text anchored in world space, such as damage numbers. Each label drifts upward
+// and fades out over its lifetime.
+pub struct FloatingText {
+    labels: Vec<Label>,
+}
+
+const TEXT_LIFE: f32 = 0.8;
+const TEXT_RISE_SPEED: f32 = 30.;
+const TEXT_SIZE: f32 = 16.;
+
+impl FloatingText {
+
+    pub fn new() -> Self {
+        Self {
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn spawn(&mut self, position: Vec2, text: String, color: Color) {
+        self.labels.push(Label {
+            position,
+            text,
+            color,
+            life: TEXT_LIFE,
+        });
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        for label in &mut self.labels {
+            label.position -= vec2(0., TEXT_RISE_SPEED * delta);
+            label.life -= delta;
+        }
+        self.labels.retain(|label| label.life > 0.);
+    }
+
+    pub fn draw(&self) {
+        for label in &self.labels {
+            let Color([r, g, b, a]) = label.color;
+            let alpha = (a as f32 * label.life / TEXT_LIFE) as u8;
+            draw_text(&label.text, label.position.x(), label.position.y(), TEXT_SIZE, Color([r, g, b, alpha]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1. / 60.;
+
+    #[test]
+    fn label_rises_and_expires_after_its_lifetime() {
+        let mut text = FloatingText::new();
+        text.spawn(vec2(10., 100.), "3".to_string(), RED);
+        for _ in 0..30 {
+            text.update(DT);
+        }
+        assert_eq!(text.labels.len(), 1);
+        let label = &text.labels[0];
+        assert_eq!(label.text, "3");
+        assert_eq!(label.position.x(), 10.);
+        assert!((label.position.y() - (100. - TEXT_RISE_SPEED * 0.5)).abs() < 0.01);
+        for _ in 0..((TEXT_LIFE - 0.5) / DT) as usize + 2 {
+            text.update(DT);
+        }
+        assert!(text.labels.is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use nalgebra as na;
+use nphysics2d::object::{DefaultBodySet, DefaultColliderSet};
+use crate::enemy::Enemy;
+
+#[derive(Clone, Copy, Debug)]
+pub enum FormationShape {
+    // `count` enemies in a row, left to right.
+    Line { count: usize, spacing: f32 },
+    // Row by row, top to bottom.
+    Grid { columns: usize, rows: usize, spacing: f32 },
+    // Spread evenly along an arc above the anchor, `spread` degrees wide.
+    Arc { count: usize, radius: f32, spread: f32 },
+}
+
+// A group of enemies declared once in the level and expanded into individual spawns. The anchor
+// is the first enemy of a line or grid and the centre of an arc.
+pub struct Formation {
+    pub anchor: na::Vector2<f32>,
+    pub shape: FormationShape,
+    pub health: u32,
+}
+
+impl Formation {
+
+    pub fn offsets(&self) -> Vec<na::Vector2<f32>> {
+        match self.shape {
+            FormationShape::Line { count, spacing } => (0..count)
+                .map(|i| na::Vector2::new(i as f32 * spacing, 0.))
+                .collect(),
+            FormationShape::Grid { columns, rows, spacing } => (0..rows)
+                .flat_map(|row| (0..columns).map(move |column| na::Vector2::new(column as f32 * spacing, row as f32 * spacing)))
+                .collect(),
+            FormationShape::Arc { count, radius, spread } => (0..count)
+                .map(|i| {
+                    let t = if count > 1 { i as f32 / (count - 1) as f32 } else { 0.5 };
+                    let angle = (-90. - spread / 2. + t * spread).to_radians();
+                    na::Vector2::new(angle.cos(), angle.sin()) * radius
+                })
+                .collect(),
+        }
+    }
+
+    pub fn spawn(&self, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>) -> Vec<Enemy> {
+        self.offsets().into_iter()
+            .map(|offset| {
+                let position = self.anchor + offset;
+                Enemy::new(position.x, position.y, bodies, colliders, self.health)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::TestWorld;
+
+    fn formation(shape: FormationShape) -> Formation {
+        Formation { anchor: na::Vector2::new(100., 200.), shape, health: 2 }
+    }
+
+    fn assert_near(actual: na::Vector2<f32>, expected: na::Vector2<f32>) {
+        assert!((actual - expected).norm() < 1e-4, "{:?} is not {:?}", actual, expected);
+    }
+
+    #[test]
+    fn line_offsets() {
+        let offsets = formation(FormationShape::Line { count: 3, spacing: 25. }).offsets();
+        assert_eq!(offsets, vec![na::Vector2::new(0., 0.), na::Vector2::new(25., 0.), na::Vector2::new(50., 0.)]);
+    }
+
+    #[test]
+    fn grid_spawns_row_by_row() {
+        let mut world = TestWorld::new();
+        let formation = formation(FormationShape::Grid { columns: 3, rows: 2, spacing: 30. });
+        let enemies = formation.spawn(&mut world.bodies, &mut world.colliders);
+        assert_eq!(enemies.len(), 6);
+        let expected = [(0., 0.), (30., 0.), (60., 0.), (0., 30.), (30., 30.), (60., 30.)];
+        for (enemy, &(x, y)) in enemies.iter().zip(expected.iter()) {
+            assert_near(enemy.object.position(&world.bodies) - formation.anchor, na::Vector2::new(x, y));
+            assert_eq!(enemy.health, 2);
+        }
+    }
+
+    #[test]
+    fn arc_spreads_above_the_anchor() {
+        let offsets = formation(FormationShape::Arc { count: 3, radius: 10., spread: 90. }).offsets();
+        let side = 10. / 2_f32.sqrt();
+        assert_near(offsets[0], na::Vector2::new(-side, -side));
+        assert_near(offsets[1], na::Vector2::new(0., -10.));
+        assert_near(offsets[2], na::Vector2::new(side, -side));
+    }
+
+    #[test]
+    fn single_enemy_arc_is_straight_above() {
+        let offsets = formation(FormationShape::Arc { count: 1, radius: 10., spread: 90. }).offsets();
+        assert_eq!(offsets.len(), 1);
+        assert_near(offsets[0], na::Vector2::new(0., -10.));
+    }
+}
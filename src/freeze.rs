@@ -0,0 +1,87 @@
+use nphysics2d::algebra::Velocity2;
+use nphysics2d::object::{Body, BodyStatus, DefaultBodyHandle, DefaultBodySet};
+
+struct Frozen {
+    handle: DefaultBodyHandle,
+    status: BodyStatus,
+    velocity: Velocity2<f32>,
+}
+
+// Debug tool that pins single bodies in place while the rest of the world keeps running.
+// A frozen body is made kinematic with no velocity, and gets its old status and velocity
+// back when unfrozen.
+pub struct Freezer {
+    frozen: Vec<Frozen>,
+}
+
+impl Freezer {
+
+    pub fn new() -> Self {
+        Self {
+            frozen: Vec::new(),
+        }
+    }
+
+    // Freezes `handle`, or restores it if it is already frozen. Static bodies are left alone.
+    pub fn toggle(&mut self, bodies: &mut DefaultBodySet<f32>, handle: DefaultBodyHandle) {
+        let body = match bodies.rigid_body_mut(handle) {
+            Some(body) => body,
+            None => return,
+        };
+        if let Some(index) = self.frozen.iter().position(|frozen| frozen.handle == handle) {
+            let frozen = self.frozen.remove(index);
+            body.set_status(frozen.status);
+            body.set_velocity(frozen.velocity);
+            body.activate();
+        } else if body.status() != BodyStatus::Static {
+            self.frozen.push(Frozen {
+                handle,
+                status: body.status(),
+                velocity: *body.velocity(),
+            });
+            body.set_status(BodyStatus::Kinematic);
+            body.set_velocity(Velocity2::zero());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra as na;
+    use nphysics2d::object::RigidBodyDesc;
+    use crate::physics::testing::TestWorld;
+
+    #[test]
+    fn frozen_box_stops_while_the_other_keeps_falling() {
+        let mut world = TestWorld::new();
+        let frozen = world.dynamic(0., 0., 10., 10.);
+        let falling = world.dynamic(50., 0., 10., 10.);
+        for object in &[&frozen, &falling] {
+            object.set_linear_velocity(&mut world.bodies, na::Vector2::new(0., 20.));
+        }
+        let mut freezer = Freezer::new();
+        freezer.toggle(&mut world.bodies, frozen.body_handle);
+        let (frozen_start, falling_start) = (frozen.position(&world.bodies), falling.position(&world.bodies));
+        for _ in 0..30 {
+            world.step();
+        }
+        assert_eq!(frozen.position(&world.bodies), frozen_start);
+        assert!(falling.position(&world.bodies).y > falling_start.y + 5.);
+
+        // Unfreezing gives it back its fall.
+        freezer.toggle(&mut world.bodies, frozen.body_handle);
+        assert_eq!(frozen.rigid_body(&world.bodies).status(), BodyStatus::Dynamic);
+        assert_eq!(frozen.linear_velocity(&world.bodies), na::Vector2::new(0., 20.));
+    }
+
+    #[test]
+    fn static_bodies_are_left_alone() {
+        let mut world = TestWorld::new();
+        let handle = world.bodies.insert(RigidBodyDesc::new().status(BodyStatus::Static).build());
+        let mut freezer = Freezer::new();
+        freezer.toggle(&mut world.bodies, handle);
+        assert_eq!(world.bodies.rigid_body(handle).unwrap().status(), BodyStatus::Static);
+        assert!(freezer.frozen.is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+use nalgebra as na;
+use nphysics2d::object::{Body, BodyStatus, DefaultBodyHandle, DefaultBodySet};
+use crate::GameObject;
+
+// How close a block's centre has to be to the hero's to be picked up.
+pub const GRAB_RANGE: f32 = 25.;
+// Space left between the hero's head and the carried block.
+pub const CARRY_GAP: f32 = 2.;
+pub const THROW_SPEED_X: f32 = 120.;
+pub const THROW_SPEED_Y: f32 = -60.;
+// Damage a thrown enemy deals to the enemy it hits.
+pub const THROWN_ENEMY_DAMAGE: u32 = 2;
+
+// A block, or a stunned enemy, held above the hero's head. While carried it is kinematic, so gravity and contacts
+// don't pull it off the hero; it just follows him every tick.
+pub struct Grab {
+    carried: Option<DefaultBodyHandle>,
+    // Distance from the hero's centre up to the carried block's centre.
+    lift: f32,
+}
+
+impl Grab {
+
+    pub fn new() -> Self {
+        Self {
+            carried: None,
+            lift: 0.,
+        }
+    }
+
+    pub fn is_carrying(&self) -> bool {
+        self.carried.is_some()
+    }
+
+    pub fn carried(&self) -> Option<DefaultBodyHandle> {
+        self.carried
+    }
+
+    // Picks `object` up if the hands are free and it is within reach.
+    pub fn try_grab(&mut self, bodies: &mut DefaultBodySet<f32>, hero: &GameObject, object: &GameObject) -> bool {
+        if self.is_carrying() {
+            return false;
+        }
+        if (object.position(bodies) - hero.position(bodies)).norm() > GRAB_RANGE {
+            return false;
+        }
+        if let Some(body) = bodies.rigid_body_mut(object.body_handle) {
+            body.set_status(BodyStatus::Kinematic);
+        }
+        self.carried = Some(object.body_handle);
+        self.lift = hero.height + object.height + CARRY_GAP;
+        true
+    }
+
+    // Keeps the carried block above the hero, moving with him.
+    pub fn carry(&self, bodies: &mut DefaultBodySet<f32>, hero: &GameObject) {
+        let handle = match self.carried {
+            Some(handle) => handle,
+            None => return,
+        };
+        let position = hero.position(bodies) - na::Vector2::new(0., self.lift);
+        let velocity = hero.linear_velocity(bodies);
+        if let Some(body) = bodies.rigid_body_mut(handle) {
+            body.set_position(na::Isometry2::translation(position.x, position.y));
+            body.set_linear_velocity(velocity);
+        }
+    }
+
+    // Lets go of the block as a free dynamic body, flying forward and up toward `facing` (1 right,
+    // -1 left), or straight along `aim` at the same speed when there is one.
+    pub fn throw(&mut self, bodies: &mut DefaultBodySet<f32>, facing: f32, aim: Option<na::Vector2<f32>>) {
+        let velocity = match aim {
+            Some(aim) => aim * THROW_SPEED_X.hypot(THROW_SPEED_Y),
+            None => na::Vector2::new(THROW_SPEED_X * facing, THROW_SPEED_Y),
+        };
+        self.let_go(bodies, velocity);
+    }
+
+    // Drops the block where it is, keeping the velocity it was carried with.
+    pub fn release(&mut self, bodies: &mut DefaultBodySet<f32>) {
+        let velocity = match self.carried {
+            Some(handle) => bodies.rigid_body(handle).map_or(na::zero(), |body| body.velocity().linear),
+            None => return,
+        };
+        self.let_go(bodies, velocity);
+    }
+
+    fn let_go(&mut self, bodies: &mut DefaultBodySet<f32>, velocity: na::Vector2<f32>) {
+        let handle = match self.carried.take() {
+            Some(handle) => handle,
+            None => return,
+        };
+        if let Some(body) = bodies.rigid_body_mut(handle) {
+            body.set_status(BodyStatus::Dynamic);
+            body.set_linear_velocity(velocity);
+            body.activate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::TestWorld;
+
+    #[test]
+    fn carried_block_is_thrown_forward_as_a_dynamic_body() {
+        let mut world = TestWorld::new();
+        let hero = world.dynamic(0., 0., 10., 10.);
+        let block = world.dynamic(20., 0., 10., 10.);
+        let mut grab = Grab::new();
+        assert!(grab.try_grab(&mut world.bodies, &hero, &block));
+        hero.set_linear_velocity(&mut world.bodies, na::Vector2::new(30., 0.));
+        for _ in 0..10 {
+            world.step();
+            grab.carry(&mut world.bodies, &hero);
+        }
+        let (hero_position, block_position) = (hero.position(&world.bodies), block.position(&world.bodies));
+        assert_eq!(block_position, hero_position - na::Vector2::new(0., 20. + CARRY_GAP));
+        grab.throw(&mut world.bodies, 1., None);
+        assert!(!grab.is_carrying());
+        let body = block.rigid_body(&world.bodies);
+        assert_eq!(body.status(), BodyStatus::Dynamic);
+        assert_eq!(body.velocity().linear, na::Vector2::new(THROW_SPEED_X, THROW_SPEED_Y));
+    }
+
+    #[test]
+    fn cannot_grab_while_carrying_or_out_of_reach() {
+        let mut world = TestWorld::new();
+        let hero = world.dynamic(0., 0., 10., 10.);
+        let block = world.dynamic(20., 0., 10., 10.);
+        let other = world.dynamic(-20., 0., 10., 10.);
+        let far = world.dynamic(GRAB_RANGE + 10., 0., 10., 10.);
+        let mut grab = Grab::new();
+        assert!(!grab.try_grab(&mut world.bodies, &hero, &far));
+        assert!(grab.try_grab(&mut world.bodies, &hero, &block));
+        assert!(!grab.try_grab(&mut world.bodies, &hero, &other));
+        assert_eq!(grab.carried(), Some(block.body_handle));
+    }
+}
@@ -0,0 +1,319 @@
+use nalgebra as na;
+use nphysics2d::object::{Body, DefaultBodySet, DefaultColliderSet};
+use crate::GameObject;
+
+pub const HERO_HEALTH: u32 = 3;
+pub const IDLE_ANIMATION: usize = 0;
+pub const RUN_ANIMATION: usize = 1;
+pub const DIE_ANIMATION: usize = 4;
+pub const CLIMB_ANIMATION: usize = 5;
+pub const SKID_ANIMATION: usize = 6;
+// Seconds of invulnerability after taking a hit.
+const INVULNERABILITY: f32 = 1.;
+const FLASH_INTERVAL: f32 = 0.1;
+pub const RUN_SPEED: f32 = 60.;
+pub const WALK_SPEED: f32 = 25.;
+// Horizontal impulse each tick the hero is steered.
+pub const MOVE_IMPULSE: f32 = 250.;
+// Steering against ground movement faster than this skids. While skidding the hero turns
+// around with only this share of the usual impulse.
+pub const SKID_SPEED: f32 = 40.;
+pub const SKID_ACCELERATION: f32 = 0.4;
+// How fast the hero speeds up sliding down a slope that's too steep to stand on.
+pub const SLIDE_ACCELERATION: f32 = 30.;
+// Seconds of invulnerability after spawning or respawning.
+const SPAWN_PROTECTION: f32 = 2.;
+// Seconds between dying and respawning.
+const RESPAWN_DELAY: f32 = 2.;
+pub const JUMP_SPEED: f32 = 40.;
+pub const CLIMB_SPEED: f32 = 30.;
+// Sideways speed of a jump off a wall.
+pub const WALL_JUMP_SPEED: f32 = 50.;
+pub const DASH_SPEED: f32 = 150.;
+pub const DASH_DURATION: f32 = 0.15;
+pub const DASH_COOLDOWN: f32 = 0.6;
+// Seconds after leaving the ground, e.g. walking off a ledge, during which a jump still works.
+const COYOTE_TIME: f32 = 0.1;
+pub const HERO_SHOT_SPEED: f32 = 150.;
+pub const HERO_SHOT_DAMAGE: u32 = 1;
+// How many enemies a single shot goes through.
+pub const HERO_SHOT_PIERCE: u8 = 2;
+pub const HERO_SHOT_COOLDOWN: f32 = 0.5;
+pub const KNOCKBACK_SPEED: f32 = 60.;
+pub const KNOCKBACK_LIFT: f32 = 30.;
+// Knockback never leaves the hero faster than this, however he was already moving.
+pub const MAX_KNOCKBACK_SPEED: f32 = 90.;
+// Seconds after a knockback during which the hero's collider uses CCD, so the shove can't
+// carry him through a thin wall in a single step.
+const KNOCKBACK_CCD: f32 = 0.3;
+
+pub struct Hero {
+    pub object: GameObject,
+    pub health: u32,
+    pub invulnerable: f32,
+    pub spawn_protection: f32,
+    // Multiplies every jump, dash and knockback speed, to make the game feel heavier or snappier.
+    pub impulse_scale: f32,
+    pub run_speed: f32,
+    pub walk_speed: f32,
+    pub walk_toggled: bool,
+    // Walking this tick, either toggled on or held.
+    pub walking: bool,
+    // On a ladder, with gravity off and Up/Down moving him directly.
+    pub climbing: bool,
+    // Slamming down in a ground pound until he lands.
+    pub pounding: bool,
+    // Turning around from a run, until he moves the new way.
+    pub skidding: bool,
+    // Overlapping water, with buoyancy, drag and slower movement.
+    pub swimming: bool,
+    // Seconds since the hero last stood on something, infinite right after a jump.
+    pub air_time: f32,
+    // Whether the double jump is still available, given back on landing.
+    pub air_jump: bool,
+    // Seconds left in the current dash, which holds a fixed horizontal speed with no gravity.
+    pub dash: f32,
+    pub dash_direction: f32,
+    pub dash_cooldown: f32,
+    // Seconds until the hero can shoot again.
+    pub shot_cooldown: f32,
+    // Seconds left in the current knockback.
+    pub knockback: f32,
+    // Seconds since the hero died.
+    pub death: Option<f32>,
+}
+
+impl Hero {
+
+    pub fn new(x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>) -> Self {
+        Self {
+            object: GameObject::new(x, y, bodies, colliders, 10., 10., 75., 1.),
+            health: HERO_HEALTH,
+            invulnerable: SPAWN_PROTECTION,
+            spawn_protection: SPAWN_PROTECTION,
+            impulse_scale: 1.,
+            run_speed: RUN_SPEED,
+            walk_speed: WALK_SPEED,
+            walk_toggled: false,
+            walking: false,
+            climbing: false,
+            swimming: false,
+            pounding: false,
+            skidding: false,
+            air_time: 0.,
+            air_jump: true,
+            dash: 0.,
+            dash_direction: 1.,
+            dash_cooldown: 0.,
+            shot_cooldown: 0.,
+            knockback: 0.,
+            death: None,
+        }
+    }
+
+    // Returns whether the hit landed.
+    pub fn hurt(&mut self, damage: u32) -> bool {
+        if self.invulnerable > 0. || self.is_dead() {
+            return false;
+        }
+        self.health = self.health.saturating_sub(damage);
+        self.invulnerable = INVULNERABILITY;
+        if self.health == 0 {
+            self.death = Some(0.);
+        }
+        true
+    }
+
+    // Shoves the hero toward `direction` (1 right, -1 left) with a capped change in velocity.
+    pub fn knock_back(&mut self, bodies: &mut DefaultBodySet<f32>, direction: f32) {
+        let mut velocity = self.object.linear_velocity(bodies) + na::Vector2::new(KNOCKBACK_SPEED * direction, -KNOCKBACK_LIFT) * self.impulse_scale;
+        let speed = velocity.norm();
+        let max_speed = MAX_KNOCKBACK_SPEED * self.impulse_scale;
+        if speed > max_speed {
+            velocity *= max_speed / speed;
+        }
+        self.object.set_linear_velocity(bodies, velocity);
+        self.knockback = KNOCKBACK_CCD;
+    }
+
+    pub fn can_jump(&self) -> bool {
+        self.air_time <= COYOTE_TIME
+    }
+
+    // Only the vertical velocity is replaced, so running off a ledge and jumping late keeps
+    // the hero's horizontal momentum.
+    pub fn jump(&mut self, bodies: &mut DefaultBodySet<f32>) {
+        let velocity = self.object.linear_velocity(bodies);
+        self.object.set_linear_velocity(bodies, na::Vector2::new(velocity.x, -JUMP_SPEED * self.impulse_scale));
+        self.air_time = f32::INFINITY;
+    }
+
+    // Jumps up and away from a wall on `wall_side` (-1 left, 1 right).
+    pub fn wall_jump(&mut self, bodies: &mut DefaultBodySet<f32>, wall_side: f32) {
+        self.object.set_linear_velocity(bodies, na::Vector2::new(-wall_side * WALL_JUMP_SPEED, -JUMP_SPEED) * self.impulse_scale);
+        self.air_time = f32::INFINITY;
+    }
+
+    // Starts a dash toward `direction` (1 right, -1 left). Returns false while on cooldown.
+    pub fn dash(&mut self, bodies: &mut DefaultBodySet<f32>, direction: f32) -> bool {
+        if self.dash_cooldown > 0. {
+            return false;
+        }
+        self.dash = DASH_DURATION;
+        self.dash_direction = direction;
+        self.dash_cooldown = DASH_COOLDOWN;
+        self.hold_dash(bodies);
+        true
+    }
+
+    pub fn is_dashing(&self) -> bool {
+        self.dash > 0.
+    }
+
+    // Keeps the dash speed every tick, and cancels out gravity by zeroing the fall.
+    pub fn hold_dash(&self, bodies: &mut DefaultBodySet<f32>) {
+        self.object.set_linear_velocity(bodies, na::Vector2::new(self.dash_direction * DASH_SPEED * self.impulse_scale, 0.));
+    }
+
+    pub fn set_climbing(&mut self, bodies: &mut DefaultBodySet<f32>, climbing: bool) {
+        self.climbing = climbing;
+        if let Some(body) = bodies.rigid_body_mut(self.object.body_handle) {
+            body.enable_gravity(!climbing);
+        }
+    }
+
+    // `direction` is -1 to climb up, 1 to climb down and 0 to hold on.
+    pub fn climb(&mut self, bodies: &mut DefaultBodySet<f32>, direction: f32) {
+        self.object.set_linear_velocity(bodies, na::Vector2::new(0., direction * CLIMB_SPEED));
+    }
+
+    pub fn is_knocked_back(&self) -> bool {
+        self.knockback > 0.
+    }
+
+    pub fn update(&mut self, delta: f32, grounded: bool) {
+        if grounded {
+            self.air_time = 0.;
+            self.air_jump = true;
+        } else {
+            self.air_time += delta;
+        }
+        self.invulnerable = (self.invulnerable - delta).max(0.);
+        self.knockback = (self.knockback - delta).max(0.);
+        self.shot_cooldown = (self.shot_cooldown - delta).max(0.);
+        self.dash = (self.dash - delta).max(0.);
+        self.dash_cooldown = (self.dash_cooldown - delta).max(0.);
+        if let Some(death) = &mut self.death {
+            *death += delta;
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.death.is_some()
+    }
+
+    pub fn should_respawn(&self) -> bool {
+        self.death.is_some_and(|death| death >= RESPAWN_DELAY)
+    }
+
+    pub fn respawn(&mut self, bodies: &mut DefaultBodySet<f32>, spawn: na::Vector2<f32>) {
+        self.set_climbing(bodies, false);
+        self.health = HERO_HEALTH;
+        self.invulnerable = self.spawn_protection;
+        self.death = None;
+        self.dash = 0.;
+        let body = bodies.rigid_body_mut(self.object.body_handle).unwrap();
+        body.set_position(na::Isometry2::new(spawn, 0.));
+        body.set_linear_velocity(na::Vector2::zeros());
+    }
+
+    // Blink while invulnerable so the player can tell they can't be hit.
+    pub fn is_visible(&self) -> bool {
+        self.invulnerable <= 0. || (self.invulnerable / FLASH_INTERVAL) as u32 & 1 == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{self, testing::{TestWorld, DT}};
+
+    // Knocks the hero right at `impulse_scale` times the usual strength toward a wall two pixels
+    // thick, with CCD on during the knockback like the world does. Returns where he and the wall
+    // end up, and how fast he is still moving right.
+    fn knock_into_wall(impulse_scale: f32) -> (f32, f32, f32) {
+        let mut world = TestWorld::new();
+        let wall = world.fixed(60., 0., 1., 80.);
+        let mut hero = Hero::new(0., 0., &mut world.bodies, &mut world.colliders);
+        hero.impulse_scale = impulse_scale;
+        hero.knock_back(&mut world.bodies, 1.);
+        for _ in 0..30 {
+            if let Some(collider) = world.colliders.get_mut(hero.object.collider_handle) {
+                collider.enable_ccd(hero.is_knocked_back());
+            }
+            world.step();
+            hero.update(DT, false);
+        }
+        (hero.object.position(&world.bodies).x, wall.position(&world.bodies).x, hero.object.linear_velocity(&world.bodies).x)
+    }
+
+    #[test]
+    fn running_off_a_ledge_keeps_momentum_and_allows_a_late_jump() {
+        let mut world = TestWorld::new();
+        world.fixed(0., 100., 50., 5.);
+        let mut hero = Hero::new(45., 85., &mut world.bodies, &mut world.colliders);
+        hero.object.set_linear_velocity(&mut world.bodies, na::Vector2::new(30., 0.));
+        // Speed on the last tick on the ledge, friction has slowed him a little by then.
+        let mut ledge_speed = None;
+        for _ in 0..60 {
+            world.step();
+            let grounded = physics::is_grounded(&world.geometrical_world, &world.colliders, hero.object.collider_handle);
+            hero.update(DT, grounded);
+            if grounded {
+                ledge_speed = Some(hero.object.linear_velocity(&world.bodies).x);
+            } else if ledge_speed.is_some() {
+                break;
+            }
+        }
+        let ledge_speed = ledge_speed.expect("never stood on the ledge");
+        let velocity = hero.object.linear_velocity(&world.bodies);
+        assert!(velocity.x > 25. && (velocity.x - ledge_speed).abs() < 0.1, "left the ledge at {} from {}", velocity.x, ledge_speed);
+        assert!(hero.can_jump());
+        hero.jump(&mut world.bodies);
+        assert_eq!(hero.object.linear_velocity(&world.bodies), na::Vector2::new(velocity.x, -JUMP_SPEED));
+    }
+
+    #[test]
+    fn coyote_time_runs_out() {
+        let mut world = TestWorld::new();
+        let mut hero = Hero::new(0., 0., &mut world.bodies, &mut world.colliders);
+        hero.update(DT, true);
+        for _ in 0..(COYOTE_TIME / DT) as usize {
+            hero.update(DT, false);
+        }
+        assert!(hero.can_jump());
+        hero.update(DT, false);
+        hero.update(DT, false);
+        assert!(!hero.can_jump());
+    }
+
+    #[test]
+    fn knockback_is_capped() {
+        let mut world = TestWorld::new();
+        let mut hero = Hero::new(0., 0., &mut world.bodies, &mut world.colliders);
+        hero.knock_back(&mut world.bodies, 1.);
+        hero.knock_back(&mut world.bodies, 1.);
+        let velocity = hero.object.linear_velocity(&world.bodies);
+        assert!(velocity.norm() <= MAX_KNOCKBACK_SPEED + 0.01);
+        assert!(velocity.x > 0. && velocity.y < 0.);
+        assert!(hero.is_knocked_back());
+    }
+
+    #[test]
+    fn hard_knockback_stops_against_a_thin_wall() {
+        // Fast enough to cross the wall in a single step without CCD.
+        let (hero, wall, speed) = knock_into_wall(30.);
+        assert!(hero < wall, "the hero went through the wall to {}", hero);
+        assert!(speed < 1., "still moving at {}", speed);
+    }
+}
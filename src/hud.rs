@@ -0,0 +1,225 @@
+use macroquad::*;
+use crate::debug::DebugOverlays;
+use crate::hero::HERO_HEALTH;
+use crate::input::InputState;
+use crate::world::GameWorld;
+
+const HEALTH_ICON_SIZE: f32 = 12.;
+const HEALTH_ICON_GAP: f32 = 4.;
+const DEBUG_TEXT_SIZE: f32 = 16.;
+// There's no text measuring, so the readout reserves a box wide enough for its longest line.
+const DEBUG_TEXT_WIDTH: f32 = 170.;
+const COIN_TEXT_SIZE: f32 = 20.;
+const COIN_TEXT_WIDTH: f32 = 90.;
+const INDICATOR_SIZE: f32 = 18.;
+const INDICATOR_GAP: f32 = 3.;
+const INDICATOR_COLOR: Color = Color([255, 220, 80, 255]);
+
+// One key cap of the input display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Indicator {
+    Left,
+    Right,
+    Up,
+    Down,
+    Jump,
+    Attack,
+    Shoot,
+    Dash,
+}
+
+// In display order.
+pub const INDICATORS: [Indicator; 8] = [
+    Indicator::Left, Indicator::Up, Indicator::Down, Indicator::Right,
+    Indicator::Jump, Indicator::Attack, Indicator::Shoot, Indicator::Dash,
+];
+
+impl Indicator {
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Indicator::Left => "<",
+            Indicator::Right => ">",
+            Indicator::Up => "^",
+            Indicator::Down => "v",
+            Indicator::Jump => "J",
+            Indicator::Attack => "A",
+            Indicator::Shoot => "S",
+            Indicator::Dash => "D",
+        }
+    }
+
+    pub fn is_active(self, input: &InputState) -> bool {
+        match self {
+            Indicator::Left => input.left,
+            Indicator::Right => input.right,
+            Indicator::Up => input.up,
+            Indicator::Down => input.down,
+            Indicator::Jump => input.jump,
+            Indicator::Attack => input.attack,
+            Indicator::Shoot => input.shoot,
+            Indicator::Dash => input.dash,
+        }
+    }
+}
+
+// The indicators the input display lights up for `input`, in display order.
+pub fn active_indicators(input: &InputState) -> Vec<Indicator> {
+    INDICATORS.iter().copied().filter(|indicator| indicator.is_active(input)).collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HudElement {
+    pub anchor: Anchor,
+    // Distance from the anchored corner, pointing into the screen.
+    pub offset: Vec2,
+    pub scale: f32,
+}
+
+pub struct HudLayout {
+    pub health: HudElement,
+    // FPS, tick and time readout for correlating bug reports, each line shown when its overlay
+    // is on.
+    pub debug: HudElement,
+    pub inputs: HudElement,
+    pub coins: HudElement,
+}
+
+impl Default for HudLayout {
+    fn default() -> Self {
+        Self {
+            health: HudElement { anchor: Anchor::TopLeft, offset: vec2(10., 10.), scale: 1. },
+            debug: HudElement { anchor: Anchor::TopRight, offset: vec2(10., 10.), scale: 1. },
+            inputs: HudElement { anchor: Anchor::BottomLeft, offset: vec2(10., 10.), scale: 1. },
+            coins: HudElement { anchor: Anchor::BottomRight, offset: vec2(10., 10.), scale: 1. },
+        }
+    }
+}
+
+// Top left corner of an element of `size` placed at `offset` from the `anchor` corner.
+pub fn anchor_position(anchor: Anchor, offset: Vec2, size: Vec2, screen: Vec2) -> Vec2 {
+    let x = match anchor {
+        Anchor::TopLeft | Anchor::BottomLeft => offset.x(),
+        Anchor::TopRight | Anchor::BottomRight => screen.x() - offset.x() - size.x(),
+    };
+    let y = match anchor {
+        Anchor::TopLeft | Anchor::TopRight => offset.y(),
+        Anchor::BottomLeft | Anchor::BottomRight => screen.y() - offset.y() - size.y(),
+    };
+    vec2(x, y)
+}
+
+impl HudElement {
+
+    pub fn position(&self, size: Vec2) -> Vec2 {
+        anchor_position(self.anchor, self.offset, size * self.scale, vec2(screen_width(), screen_height()))
+    }
+}
+
+fn draw_health(element: &HudElement, health: u32) {
+    let icon = HEALTH_ICON_SIZE * element.scale;
+    let gap = HEALTH_ICON_GAP * element.scale;
+    let size = vec2(HERO_HEALTH as f32 * (HEALTH_ICON_SIZE + HEALTH_ICON_GAP) - HEALTH_ICON_GAP, HEALTH_ICON_SIZE);
+    let position = element.position(size);
+    for i in 0..HERO_HEALTH {
+        let x = position.x() + i as f32 * (icon + gap);
+        if i < health {
+            draw_rectangle(x, position.y(), icon, icon, RED);
+        } else {
+            draw_rectangle_lines(x, position.y(), icon, icon, 2., GRAY);
+        }
+    }
+}
+
+fn draw_coins(element: &HudElement, coins: u32) {
+    let size = COIN_TEXT_SIZE * element.scale;
+    let position = element.position(vec2(COIN_TEXT_WIDTH, COIN_TEXT_SIZE));
+    draw_text(&format!("coins {}", coins), position.x(), position.y() + size, size, GOLD);
+}
+
+fn draw_debug(element: &HudElement, world: &GameWorld, real_time: f32, overlays: &DebugOverlays) {
+    let mut lines = Vec::new();
+    if overlays.fps {
+        lines.push(format!("fps {}", get_fps()));
+    }
+    if overlays.timing {
+        lines.push(format!("tick {}", world.tick));
+        lines.push(format!("game {:.2}s", world.game_time));
+        lines.push(format!("real {:.2}s", real_time));
+    }
+    let line_height = DEBUG_TEXT_SIZE * element.scale;
+    let position = element.position(vec2(DEBUG_TEXT_WIDTH, DEBUG_TEXT_SIZE * lines.len() as f32));
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, position.x(), position.y() + i as f32 * line_height, line_height, DARKGRAY);
+    }
+}
+
+fn draw_inputs(element: &HudElement, input: &InputState) {
+    let size = INDICATOR_SIZE * element.scale;
+    let gap = INDICATOR_GAP * element.scale;
+    let position = element.position(vec2(INDICATORS.len() as f32 * (INDICATOR_SIZE + INDICATOR_GAP) - INDICATOR_GAP, INDICATOR_SIZE));
+    let active = active_indicators(input);
+    for (i, &indicator) in INDICATORS.iter().enumerate() {
+        let x = position.x() + i as f32 * (size + gap);
+        let color = if active.contains(&indicator) {
+            draw_rectangle(x, position.y(), size, size, INDICATOR_COLOR);
+            BLACK
+        } else {
+            draw_rectangle_lines(x, position.y(), size, size, 2., GRAY);
+            GRAY
+        };
+        draw_text(indicator.label(), x + size * 0.3, position.y() + size * 0.75, size, color);
+    }
+}
+
+// `real_time` is wall-clock seconds since the game started. `input` is only given when the input
+// display is turned on.
+pub fn draw_hud(layout: &HudLayout, world: &GameWorld, input: Option<&InputState>, real_time: f32, overlays: &DebugOverlays) {
+    draw_health(&layout.health, world.hero.health);
+    draw_coins(&layout.coins, world.coins_collected);
+    draw_debug(&layout.debug, world, real_time, overlays);
+    if let Some(input) = input {
+        draw_inputs(&layout.inputs, input);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchor_positions() {
+        let (offset, size, screen) = (vec2(10., 5.), vec2(50., 20.), vec2(800., 600.));
+        assert_eq!(anchor_position(Anchor::TopLeft, offset, size, screen), vec2(10., 5.));
+        assert_eq!(anchor_position(Anchor::TopRight, offset, size, screen), vec2(740., 5.));
+        assert_eq!(anchor_position(Anchor::BottomLeft, offset, size, screen), vec2(10., 575.));
+        assert_eq!(anchor_position(Anchor::BottomRight, offset, size, screen), vec2(740., 575.));
+    }
+
+    // Bottom right elements keep their distance from the corner whatever the screen size.
+    #[test]
+    fn bottom_right_follows_the_screen_corner() {
+        let (offset, size) = (vec2(10., 10.), vec2(50., 20.));
+        for &screen in &[vec2(800., 600.), vec2(1920., 1080.)] {
+            let position = anchor_position(Anchor::BottomRight, offset, size, screen);
+            assert_eq!(position + size + offset, screen);
+        }
+    }
+
+    #[test]
+    fn active_indicators_match_the_input() {
+        assert!(active_indicators(&InputState::default()).is_empty());
+        let input = InputState { right: true, down: true, attack: true, walk: true, ..InputState::default() };
+        assert_eq!(active_indicators(&input), vec![Indicator::Down, Indicator::Right, Indicator::Attack]);
+        let all = InputState { left: true, right: true, up: true, down: true, jump: true, attack: true, shoot: true, dash: true, ..InputState::default() };
+        assert_eq!(active_indicators(&all), INDICATORS.to_vec());
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+use nalgebra as na;
+use ncollide2d::pipeline::ContactEvent;
+use nphysics2d::object::{DefaultBodyHandle, DefaultBodySet, DefaultColliderSet};
+use nphysics2d::world::DefaultGeometricalWorld;
+use crate::audio::{AudioManager, Sound};
+
+// Contacts that start slower than this are a gentle touch and stay silent.
+pub const IMPACT_SPEED: f32 = 30.;
+// Relative speed at which an impact plays at full volume.
+const LOUD_IMPACT_SPEED: f32 = 120.;
+const QUIET_IMPACT_VOLUME: f32 = 0.3;
+// A body that just made a sound stays quiet this many seconds, so one landing settling over a few
+// contacts only plays once.
+const IMPACT_DEBOUNCE: f32 = 0.15;
+
+// Plays a sound when a contact starts hard enough. Contacts are reported after the step has
+// already resolved them, so the speed comes from the velocities recorded the tick before.
+pub struct ImpactSounds {
+    velocities: BTreeMap<DefaultBodyHandle, na::Vector2<f32>>,
+    quiet: BTreeMap<DefaultBodyHandle, f32>,
+}
+
+impl ImpactSounds {
+
+    pub fn new() -> Self {
+        Self {
+            velocities: BTreeMap::new(),
+            quiet: BTreeMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, delta: f32, geometrical_world: &DefaultGeometricalWorld<f32>, colliders: &DefaultColliderSet<f32>,
+                  bodies: &DefaultBodySet<f32>, audio: &mut AudioManager) {
+        for quiet in self.quiet.values_mut() {
+            *quiet -= delta;
+        }
+        self.quiet.retain(|_, quiet| *quiet > 0.);
+        for event in geometrical_world.contact_events().iter() {
+            let (a, b) = match event {
+                ContactEvent::Started(a, b) => (*a, *b),
+                ContactEvent::Stopped(..) => continue,
+            };
+            let (a, b) = match (colliders.get(a), colliders.get(b)) {
+                (Some(a), Some(b)) if !a.is_sensor() && !b.is_sensor() => (a.body(), b.body()),
+                _ => continue,
+            };
+            if self.quiet.contains_key(&a) || self.quiet.contains_key(&b) {
+                continue;
+            }
+            let velocity = |body| self.velocities.get(&body).copied().unwrap_or_else(na::zero);
+            let speed = (velocity(a) - velocity(b)).norm();
+            if speed < IMPACT_SPEED {
+                continue;
+            }
+            // Level geometry is built from massless bodies.
+            let is_level = |body| bodies.rigid_body(body).is_some_and(|body| body.augmented_mass().linear == 0.);
+            let sound = if is_level(a) || is_level(b) { Sound::Land } else { Sound::Thud };
+            let loudness = (speed - IMPACT_SPEED) / (LOUD_IMPACT_SPEED - IMPACT_SPEED);
+            audio.play(sound, QUIET_IMPACT_VOLUME + (1. - QUIET_IMPACT_VOLUME) * loudness.min(1.));
+            self.quiet.insert(a, IMPACT_DEBOUNCE);
+            self.quiet.insert(b, IMPACT_DEBOUNCE);
+        }
+        self.velocities.clear();
+        for (handle, _) in bodies.iter() {
+            if let Some(body) = bodies.rigid_body(handle) {
+                self.velocities.insert(handle, body.velocity().linear);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::{TestWorld, DT};
+    use crate::settings::UserSettings;
+
+    // Sounds played by a box dropped onto the ground at `speed`.
+    fn drop_onto_ground(speed: f32) -> Vec<Sound> {
+        let mut world = TestWorld::new();
+        world.fixed(0., 40., 100., 5.);
+        let object = world.dynamic(0., 20., 10., 10.);
+        object.set_linear_velocity(&mut world.bodies, na::Vector2::new(0., speed));
+        let mut impacts = ImpactSounds::new();
+        let mut audio = AudioManager::new(&UserSettings::default());
+        impacts.update(DT, &world.geometrical_world, &world.colliders, &world.bodies, &mut audio);
+        for _ in 0..60 {
+            world.step();
+            impacts.update(DT, &world.geometrical_world, &world.colliders, &world.bodies, &mut audio);
+        }
+        audio.drain().into_iter().map(|(sound, _)| sound).collect()
+    }
+
+    #[test]
+    fn hard_landing_plays_one_sound_and_a_gentle_one_none() {
+        assert_eq!(drop_onto_ground(IMPACT_SPEED * 3.), vec![Sound::Land]);
+        assert_eq!(drop_onto_ground(IMPACT_SPEED / 3.), vec![]);
+    }
+}
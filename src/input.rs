@@ -0,0 +1,268 @@
+use macroquad::*;
+use std::collections::VecDeque;
+use crate::debug::DEBUG_OVERLAYS;
+
+// The logical actions the game reacts to, sampled once per frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputState {
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+    pub jump: bool,
+    pub attack: bool,
+    pub shoot: bool,
+    pub dash: bool,
+    pub rewind: bool,
+    // Held to walk instead of run.
+    pub walk: bool,
+    pub toggle_walk: bool,
+    pub restart: bool,
+    // Picks up a nearby block, or throws the one being carried.
+    pub grab: bool,
+    // Switches between scaling the view up to fit the window and drawing it at 1x.
+    pub toggle_scale: bool,
+    // Debug: clicking a body freezes or unfreezes it, Ctrl+clicking teleports the hero there.
+    pub pick: bool,
+    pub teleport: bool,
+    pub quick_save: bool,
+    pub quick_load: bool,
+    pub pause: bool,
+    // Debug: opens the overlay menu, and the number keys toggle its entries.
+    pub debug_menu: bool,
+    pub debug_toggles: [bool; DEBUG_OVERLAYS.len()],
+    // Mouse cursor in screen pixels.
+    pub pointer: Vec2,
+}
+
+impl InputState {
+
+    pub fn read(bindings: &KeyBindings) -> Self {
+        let down = |action| is_key_down(bindings.key(action));
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        Self {
+            left: down(Action::Left),
+            right: down(Action::Right),
+            up: down(Action::Up),
+            down: down(Action::Down),
+            jump: down(Action::Jump),
+            attack: down(Action::Attack),
+            shoot: down(Action::Shoot),
+            dash: down(Action::Dash),
+            rewind: down(Action::Rewind),
+            walk: down(Action::Walk),
+            toggle_walk: down(Action::ToggleWalk),
+            restart: down(Action::Restart),
+            grab: down(Action::Grab),
+            toggle_scale: down(Action::ToggleScale),
+            pick: is_mouse_button_down(MouseButton::Left) && !ctrl,
+            teleport: is_mouse_button_down(MouseButton::Left) && ctrl,
+            quick_save: is_key_down(QUICK_SAVE_KEY),
+            quick_load: is_key_down(QUICK_LOAD_KEY),
+            pause: is_key_down(PAUSE_KEY),
+            debug_menu: is_key_down(DEBUG_MENU_KEY),
+            debug_toggles: [
+                is_key_down(DEBUG_TOGGLE_KEYS[0]),
+                is_key_down(DEBUG_TOGGLE_KEYS[1]),
+                is_key_down(DEBUG_TOGGLE_KEYS[2]),
+                is_key_down(DEBUG_TOGGLE_KEYS[3]),
+            ],
+            pointer: {
+                let (x, y) = mouse_position();
+                vec2(x, y)
+            },
+        }
+    }
+}
+
+// Keys that can't be rebound.
+const QUICK_SAVE_KEY: KeyCode = KeyCode::F5;
+const QUICK_LOAD_KEY: KeyCode = KeyCode::F9;
+const PAUSE_KEY: KeyCode = KeyCode::P;
+const DEBUG_MENU_KEY: KeyCode = KeyCode::F1;
+const DEBUG_TOGGLE_KEYS: [KeyCode; DEBUG_OVERLAYS.len()] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+const FIXED_KEYS: [(KeyCode, &str); 8] = [
+    (QUICK_SAVE_KEY, "quick save"),
+    (QUICK_LOAD_KEY, "quick load"),
+    (PAUSE_KEY, "pause"),
+    (DEBUG_MENU_KEY, "debug menu"),
+    (DEBUG_TOGGLE_KEYS[0], "debug toggle 1"),
+    (DEBUG_TOGGLE_KEYS[1], "debug toggle 2"),
+    (DEBUG_TOGGLE_KEYS[2], "debug toggle 3"),
+    (DEBUG_TOGGLE_KEYS[3], "debug toggle 4"),
+];
+
+// Gameplay actions that can be rebound. Pause, saving and the debug keys stay fixed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Left,
+    Right,
+    Up,
+    Down,
+    Jump,
+    Attack,
+    Shoot,
+    Dash,
+    Rewind,
+    Walk,
+    ToggleWalk,
+    Restart,
+    Grab,
+    ToggleScale,
+}
+
+pub const ACTIONS: [Action; 14] = [
+    Action::Left, Action::Right, Action::Up, Action::Down, Action::Jump, Action::Attack, Action::Shoot,
+    Action::Dash, Action::Rewind, Action::Walk, Action::ToggleWalk, Action::Restart, Action::Grab,
+    Action::ToggleScale,
+];
+
+impl Action {
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Jump => "jump",
+            Action::Attack => "attack",
+            Action::Shoot => "shoot",
+            Action::Dash => "dash",
+            Action::Rewind => "rewind",
+            Action::Walk => "walk",
+            Action::ToggleWalk => "toggle walk",
+            Action::Restart => "restart",
+            Action::Grab => "grab",
+            Action::ToggleScale => "toggle scale",
+        }
+    }
+}
+
+// What a key being rebound is already used for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Conflict {
+    Action(Action),
+    // One of the `FIXED_KEYS`.
+    Fixed(&'static str),
+}
+
+impl Conflict {
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Conflict::Action(action) => action.name(),
+            Conflict::Fixed(name) => name,
+        }
+    }
+}
+
+// One key per action, in `ACTIONS` order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyBindings {
+    keys: [KeyCode; ACTIONS.len()],
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: [
+                KeyCode::Left, KeyCode::Right, KeyCode::Up, KeyCode::Down, KeyCode::Space, KeyCode::Z, KeyCode::V,
+                KeyCode::A, KeyCode::R, KeyCode::LeftShift, KeyCode::C, KeyCode::Backspace, KeyCode::X,
+                KeyCode::F11,
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys[action as usize]
+    }
+
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        self.keys[action as usize] = key;
+    }
+
+    // Another action or a fixed key already using `key`, if any.
+    pub fn conflict(&self, action: Action, key: KeyCode) -> Option<Conflict> {
+        if let Some(&(_, name)) = FIXED_KEYS.iter().find(|(fixed, _)| *fixed == key) {
+            return Some(Conflict::Fixed(name));
+        }
+        ACTIONS.iter().copied().find(|&other| other != action && self.key(other) == key).map(Conflict::Action)
+    }
+}
+
+// Holds inputs back a fixed number of ticks before they reach the simulation, the building block
+// for rollback netcode. Until the buffer fills up the simulation sees no input.
+pub struct InputDelay {
+    delay: usize,
+    queue: VecDeque<InputState>,
+}
+
+impl InputDelay {
+
+    pub fn new(delay: usize) -> Self {
+        Self {
+            delay,
+            queue: VecDeque::with_capacity(delay + 1),
+        }
+    }
+
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+
+    // Queues this tick's input and returns the one from `delay` ticks ago.
+    pub fn push(&mut self, input: InputState) -> InputState {
+        self.queue.push_back(input);
+        if self.queue.len() > self.delay {
+            self.queue.pop_front().unwrap_or_default()
+        } else {
+            InputState::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delayed_input_comes_out_two_ticks_later() {
+        let mut delay = InputDelay::new(2);
+        let jump = InputState { jump: true, ..InputState::default() };
+        assert_eq!(delay.push(jump), InputState::default());
+        assert_eq!(delay.push(InputState::default()), InputState::default());
+        assert_eq!(delay.push(InputState::default()), jump);
+        assert_eq!(delay.push(InputState::default()), InputState::default());
+    }
+
+    #[test]
+    fn no_delay_passes_input_straight_through() {
+        let mut delay = InputDelay::new(0);
+        let jump = InputState { jump: true, ..InputState::default() };
+        assert_eq!(delay.push(jump), jump);
+    }
+
+    #[test]
+    fn conflict_reports_other_actions_using_the_key() {
+        let bindings = KeyBindings::default();
+        assert_eq!(bindings.conflict(Action::Jump, KeyCode::Z), Some(Conflict::Action(Action::Attack)));
+        assert_eq!(bindings.conflict(Action::Jump, KeyCode::Space), None);
+        assert_eq!(bindings.conflict(Action::Jump, KeyCode::Q), None);
+    }
+
+    #[test]
+    fn conflict_reports_fixed_keys() {
+        let bindings = KeyBindings::default();
+        assert_eq!(bindings.conflict(Action::Jump, KeyCode::P), Some(Conflict::Fixed("pause")));
+        assert_eq!(bindings.conflict(Action::Jump, KeyCode::F1), Some(Conflict::Fixed("debug menu")));
+        assert_eq!(bindings.conflict(Action::Jump, KeyCode::F5), Some(Conflict::Fixed("quick save")));
+        assert_eq!(bindings.conflict(Action::Jump, KeyCode::F9), Some(Conflict::Fixed("quick load")));
+        for key in &[KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4] {
+            assert!(matches!(bindings.conflict(Action::Jump, *key), Some(Conflict::Fixed(_))));
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use macroquad::*;
+use nphysics2d::object::{DefaultBodySet, DefaultColliderSet};
+use crate::GameObject;
+use crate::physics::set_sensor;
+
+const RUNG_SPACING: f32 = 10.;
+
+// A climbable zone. Its collider is a sensor, the hero only needs to overlap it.
+pub struct Ladder {
+    pub object: GameObject,
+}
+
+impl Ladder {
+
+    pub fn new(x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, width: f32, height: f32) -> Self {
+        let object = GameObject::new(x, y, bodies, colliders, width, height, 0., 0.);
+        set_sensor(colliders, object.collider_handle, true);
+        Self {
+            object,
+        }
+    }
+
+    pub fn draw(&self, bodies: &DefaultBodySet<f32>) {
+        let pos = self.object.position(bodies);
+        let (left, right) = (pos.x - self.object.width, pos.x + self.object.width);
+        let (top, bottom) = (pos.y - self.object.height, pos.y + self.object.height);
+        draw_line(left, top, left, bottom, 2., BROWN);
+        draw_line(right, top, right, bottom, 2., BROWN);
+        let mut y = top + RUNG_SPACING / 2.;
+        while y < bottom {
+            draw_line(left, y, right, y, 2., BROWN);
+            y += RUNG_SPACING;
+        }
+    }
+}
@@ -2,35 +2,126 @@ use macroquad::*;
 use std::time::Instant;
 use nalgebra as na;
 use ncollide2d::shape::{Cuboid, ShapeHandle};
-use nphysics2d::world::{DefaultMechanicalWorld, DefaultGeometricalWorld};
 use nphysics2d::object::{DefaultBodySet, DefaultColliderSet, RigidBodyDesc, ColliderDesc, BodyPartHandle, DefaultBodyHandle, DefaultColliderHandle, Body, RigidBody};
-use nphysics2d::joint::DefaultJointConstraintSet;
-use nphysics2d::force_generator::DefaultForceGeneratorSet;
-use nphysics2d::math::{Force, ForceType};
-
-async fn load_idle_animation() -> Vec<Texture2D> {
-    vec![load_texture("assets/adventurer-idle-2-00.png").await,
-         load_texture("assets/adventurer-idle-2-01.png").await,
-         load_texture("assets/adventurer-idle-2-02.png").await,
-         load_texture("assets/adventurer-idle-2-03.png").await,
+
+mod ai;
+mod assets;
+mod attack;
+mod audio;
+mod breakable;
+mod camera;
+mod coin;
+mod collisions;
+mod combo;
+mod controls;
+mod conveyor;
+mod crumble;
+mod debug;
+mod determinism;
+mod enemy;
+mod flash;
+mod floating_text;
+mod formation;
+mod freeze;
+mod grab;
+mod hero;
+mod hud;
+mod impact;
+mod input;
+mod ladder;
+mod particles;
+mod pause;
+mod physics;
+mod profile;
+mod projectile;
+mod rewind;
+mod rng;
+mod rope;
+mod save;
+mod secret;
+mod settings;
+mod shadow;
+mod slope;
+mod tick;
+mod tilemap;
+mod time_scale;
+mod water;
+mod wind;
+mod world;
+
+use assets::{load_sprite, Sprite};
+use camera::{render_scale, toggled_render_scale};
+use controls::ControlsMenu;
+use debug::DebugMenu;
+use determinism::DeterminismCheck;
+use hero::DIE_ANIMATION;
+use hud::{draw_hud, HudLayout};
+use input::{InputState, KeyBindings};
+use pause::Pause;
+use profile::{Profile, PROFILE_PATH};
+use settings::{UserSettings, SETTINGS_PATH, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+use tick::{FixedTimestep, MAX_TICKS_PER_FRAME, TICK_RATE};
+use world::{GameWorld, SNAPSHOT_PATH};
+
+async fn load_idle_animation(scale: u32) -> Vec<Sprite> {
+    vec![load_sprite("assets/adventurer-idle-2-00.png", scale).await,
+         load_sprite("assets/adventurer-idle-2-01.png", scale).await,
+         load_sprite("assets/adventurer-idle-2-02.png", scale).await,
+         load_sprite("assets/adventurer-idle-2-03.png", scale).await,
+    ]
+}
+
+async fn load_run_animation(scale: u32) -> Vec<Sprite> {
+    vec![load_sprite("assets/adventurer-run-01.png", scale).await,
+         load_sprite("assets/adventurer-run-02.png", scale).await,
+         load_sprite("assets/adventurer-run-03.png", scale).await,
+         load_sprite("assets/adventurer-run-04.png", scale).await,
+         load_sprite("assets/adventurer-run-05.png", scale).await
+    ]
+}
+
+async fn load_attack2_animation(scale: u32) -> Vec<Sprite> {
+    vec![load_sprite("assets/adventurer-attack2-00.png", scale).await,
+         load_sprite("assets/adventurer-attack2-01.png", scale).await,
+         load_sprite("assets/adventurer-attack2-02.png", scale).await,
+         load_sprite("assets/adventurer-attack2-03.png", scale).await,
+         load_sprite("assets/adventurer-attack2-04.png", scale).await,
+         load_sprite("assets/adventurer-attack2-05.png", scale).await
     ]
 }
 
-async fn load_run_animation() -> Vec<Texture2D> {
-    vec![load_texture("assets/adventurer-run-01.png").await,
-         load_texture("assets/adventurer-run-02.png").await,
-         load_texture("assets/adventurer-run-03.png").await,
-         load_texture("assets/adventurer-run-04.png").await,
-         load_texture("assets/adventurer-run-05.png").await
+async fn load_die_animation(scale: u32) -> Vec<Sprite> {
+    vec![load_sprite("assets/adventurer-die-00.png", scale).await,
+         load_sprite("assets/adventurer-die-01.png", scale).await,
+         load_sprite("assets/adventurer-die-02.png", scale).await,
+         load_sprite("assets/adventurer-die-03.png", scale).await,
+         load_sprite("assets/adventurer-die-04.png", scale).await,
+         load_sprite("assets/adventurer-die-05.png", scale).await,
+         load_sprite("assets/adventurer-die-06.png", scale).await
     ]
 }
 
-async fn load_attack1_animation() -> Vec<Texture2D> {
-    vec![load_texture("assets/adventurer-attack1-00.png").await,
-         load_texture("assets/adventurer-attack1-01.png").await,
-         load_texture("assets/adventurer-attack1-02.png").await,
-         load_texture("assets/adventurer-attack1-03.png").await,
-         load_texture("assets/adventurer-attack1-04.png").await
+async fn load_attack1_animation(scale: u32) -> Vec<Sprite> {
+    vec![load_sprite("assets/adventurer-attack1-00.png", scale).await,
+         load_sprite("assets/adventurer-attack1-01.png", scale).await,
+         load_sprite("assets/adventurer-attack1-02.png", scale).await,
+         load_sprite("assets/adventurer-attack1-03.png", scale).await,
+         load_sprite("assets/adventurer-attack1-04.png", scale).await
+    ]
+}
+
+async fn load_climb_animation(scale: u32) -> Vec<Sprite> {
+    vec![load_sprite("assets/adventurer-crnr-clmb-00.png", scale).await,
+         load_sprite("assets/adventurer-crnr-clmb-01.png", scale).await,
+         load_sprite("assets/adventurer-crnr-clmb-02.png", scale).await,
+         load_sprite("assets/adventurer-crnr-clmb-03.png", scale).await,
+         load_sprite("assets/adventurer-crnr-clmb-04.png", scale).await
+    ]
+}
+
+async fn load_slide_animation(scale: u32) -> Vec<Sprite> {
+    vec![load_sprite("assets/adventurer-slide-00.png", scale).await,
+         load_sprite("assets/adventurer-slide-01.png", scale).await
     ]
 }
 
@@ -72,97 +163,136 @@ impl GameObject {
     pub fn rigid_body_mut<'a>(&self, bodies: &'a mut DefaultBodySet<f32>) -> &'a mut dyn Body<f32> {
         bodies.get_mut(self.body_handle).unwrap()
     }
+
+    pub fn position(&self, bodies: &DefaultBodySet<f32>) -> na::Vector2<f32> {
+        self.rigid_body(bodies).position().translation.vector
+    }
+
+    pub fn linear_velocity(&self, bodies: &DefaultBodySet<f32>) -> na::Vector2<f32> {
+        self.rigid_body(bodies).velocity().linear
+    }
+
+    pub fn set_linear_velocity(&self, bodies: &mut DefaultBodySet<f32>, velocity: na::Vector2<f32>) {
+        bodies.rigid_body_mut(self.body_handle).unwrap().set_linear_velocity(velocity);
+    }
 }
 
 
+const WALK_CADENCE: f64 = 1.8;
+
 #[macroquad::main("Game")]
 async fn main() {
-    let idle_animation = load_idle_animation().await;
-    let run_animation = load_run_animation().await;
-    let attack_1_animation = load_attack1_animation().await;
-    let animations = vec![idle_animation, run_animation, attack_1_animation];
+    let mut settings = UserSettings::load(SETTINGS_PATH).unwrap_or_default();
+    let mut settings_modified = UserSettings::modified(SETTINGS_PATH);
+    let scale = settings.asset_scale;
+    let idle_animation = load_idle_animation(scale).await;
+    let run_animation = load_run_animation(scale).await;
+    let attack_1_animation = load_attack1_animation(scale).await;
+    let attack_2_animation = load_attack2_animation(scale).await;
+    let die_animation = load_die_animation(scale).await;
+    let climb_animation = load_climb_animation(scale).await;
+    let slide_animation = load_slide_animation(scale).await;
+    let animations = vec![idle_animation, run_animation, attack_1_animation, attack_2_animation, die_animation, climb_animation, slide_animation];
     let mut current_frame = 0;
     let mut timeline = Instant::now();
     let step = 200.0;
     let mut current_animation = 0;
-    let mut flip = false;
-    let mut mechanical_world = DefaultMechanicalWorld::new(na::Vector2::new(0.0, 9.81));
-    let mut geometrical_world = DefaultGeometricalWorld::new();
-    let mut joint_constraints = DefaultJointConstraintSet::new();
-    let mut force_generators = DefaultForceGeneratorSet::new();
-    let mut bodies = DefaultBodySet::new();
-    let mut colliders = DefaultColliderSet::new();
-
-    let ground = GameObject::new(screen_width()/2., 480., &mut bodies, &mut colliders, screen_width(), 10., 0., 0.);
-    let block = GameObject::new(100., 400., &mut bodies, &mut colliders, 10., 10., 75., 1.);
-    let hero = GameObject::new(10., 350., &mut bodies, &mut colliders, 10., 10., 75., 1.);
-    mechanical_world.maintain(&mut geometrical_world,
-                              &mut bodies,
-                              &mut colliders,
-                              &mut joint_constraints,);
+    let profile = Profile::load(PROFILE_PATH).unwrap_or_default();
+    let mut determinism_check = if cfg!(debug_assertions) && settings.determinism_check {
+        Some(DeterminismCheck::new(GameWorld::new(&settings, profile.clone())))
+    } else {
+        None
+    };
+    let mut world = GameWorld::new(&settings, profile);
+    let mut timestep = FixedTimestep::new(TICK_RATE, MAX_TICKS_PER_FRAME);
+    let hud_layout = HudLayout::default();
+    let mut pause = Pause::new();
+    let mut debug_menu = DebugMenu::new();
+    let mut bindings = KeyBindings::default();
+    let mut controls = ControlsMenu::new();
+    let mut toggle_scale_held = false;
+    let start = Instant::now();
     loop {
-        let delta = get_frame_time();
+        // Edits to the settings file apply as soon as it's saved, except the asset scale, which
+        // only picks the sprites when the game starts.
+        let modified = UserSettings::modified(SETTINGS_PATH);
+        if modified != settings_modified {
+            settings_modified = modified;
+            if let Ok(changed) = UserSettings::load(SETTINGS_PATH) {
+                world.apply_settings(&changed);
+                if let Some(check) = &mut determinism_check {
+                    check.apply_settings(&changed);
+                }
+                settings = changed;
+            }
+        }
+        controls.update(&mut bindings);
+        let input = InputState::read(&bindings);
+        // This macroquad version fixes the window mode when it opens, so the toggle switches what
+        // the view does in the window instead: scaled up to fit it, or drawn at 1x. The choice is
+        // saved like any other setting.
+        if input.toggle_scale && !toggle_scale_held {
+            settings.render_scale = toggled_render_scale(settings.render_scale);
+            if let Err(error) = settings.save(SETTINGS_PATH) {
+                eprintln!("Failed to write {}: {}", SETTINGS_PATH, error);
+            }
+            settings_modified = UserSettings::modified(SETTINGS_PATH);
+        }
+        toggle_scale_held = input.toggle_scale;
+        // There are no render targets to upscale from, so the integer scale goes into the camera
+        // instead: every world pixel still covers a whole number of screen pixels. It is worked out
+        // from the window size every frame, so resizing or fullscreening the window from outside
+        // the game rescales right away.
+        world.camera.screen = vec2(screen_width(), screen_height());
+        world.camera.render_scale = render_scale(settings.render_scale, world.camera.screen, vec2(VIRTUAL_WIDTH, VIRTUAL_HEIGHT));
+        debug_menu.update(input.debug_menu, input.debug_toggles);
+        let was_paused = pause.paused;
+        let delta = pause.update(get_frame_time(), input.pause, settings.auto_pause);
+        if settings.pause_snapshot && pause.paused && !was_paused {
+            if let Err(error) = world.write_debug_dump(SNAPSHOT_PATH) {
+                eprintln!("Failed to write {}: {}", SNAPSHOT_PATH, error);
+            }
+        }
+        // The game holds still while the controls menu is open.
+        let delta = if controls.open { 0. } else { delta };
         println!("{}", delta);
-        mechanical_world.set_timestep(delta);
-        mechanical_world.step(
-            &mut geometrical_world,
-            &mut bodies,
-            &mut colliders,
-            &mut joint_constraints,
-            &mut force_generators,
-        );
+        let elapsed = delta * world.time_scale.update(delta);
+        world.flash.update(delta);
+        for _ in 0..timestep.advance(elapsed) {
+            world.update(timestep.dt, &input);
+            if let Some(check) = &mut determinism_check {
+                check.step(&world, timestep.dt, &input);
+            }
+        }
+        // Nothing can play sounds yet, so this frame's requests are dropped.
+        world.audio.drain();
+        if world.animation != current_animation {
+            if world.animation == DIE_ANIMATION {
+                timeline = Instant::now();
+            }
+            current_animation = world.animation;
+        }
         clear_background(WHITE);
-        ground.debug_draw(&bodies);
-        block.debug_draw(&bodies);
-        hero.debug_draw(&bodies);
+        begin_mode_2d(world.camera.camera());
         let elapsed = timeline.elapsed();
-        let new_frame = elapsed.as_millis() as f64 / step;
-        if new_frame > (animations[current_animation].len() - 1) as f64 {
+        // Walking and swimming play the run cycle slower to match the lower speed.
+        let frame_step = if world.hero.walking || world.hero.swimming { step * WALK_CADENCE } else { step };
+        let new_frame = elapsed.as_millis() as f64 / frame_step;
+        if world.hero.is_dead() {
+            current_frame = (new_frame as usize).min(animations[current_animation].len() - 1);
+        } else if new_frame > (animations[current_animation].len() - 1) as f64 {
             timeline = Instant::now();
             current_frame = 0;
         } else {
             current_frame = new_frame as usize;
         }
-        let texture = animations[current_animation][current_frame];
-        let pos = hero.rigid_body(&bodies).position().translation.vector;
-        draw_texture_ex(
-            texture,
-            pos.x-texture.width()/2.,
-            pos.y-texture.height()/2.,
-            WHITE,
-            DrawTextureParams {
-                dest_size: Some(vec2(texture.width(), texture.height())),
-                source: if flip { Some(Rect {
-                    x: texture.width(),
-                    y: 0.,
-                    w: -texture.width(),
-                    h: texture.height(),
-                }) } else { None },
-                rotation: 0.,
-            },
-        );
-        if is_key_down(KeyCode::Right) {
-            let force = Force::linear(na::Vector2::new(250., 0.));
-            hero.rigid_body_mut(&mut bodies).apply_force(0, &force, ForceType::Impulse, true);
-            current_animation = 1;
-            flip = false;
-        } else if is_key_down(KeyCode::Left) {
-            let force = Force::linear(na::Vector2::new(-250., 0.));
-            hero.rigid_body_mut(&mut bodies).apply_force(0, &force, ForceType::Impulse, true);
-            flip = true;
-            current_animation = 1;
-        } else {
-            current_animation = 0;
-        }
-        if is_key_down(KeyCode::Space) {
-            let force = Force::linear(na::Vector2::new(0., -200.));
-            hero.rigid_body_mut(&mut bodies).apply_force(0, &force, ForceType::Impulse, true);
-        }
-
-        if is_key_down(KeyCode::Z) {
-            current_animation = 2;
-        }
-
+        world.draw(animations[current_animation][current_frame], &debug_menu.overlays);
+        end_mode_2d();
+        draw_hud(&hud_layout, &world, settings.input_display.then_some(&input), start.elapsed().as_secs_f32(), &debug_menu.overlays);
+        world.flash.draw();
+        debug_menu.draw();
+        controls.draw(&bindings);
+        pause.draw();
         next_frame().await
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,62 @@
+use macroquad::*;
+use nalgebra as na;
+use ncollide2d::bounding_volume::AABB;
+
+struct Particle {
+    position: Vec2,
+    velocity: Vec2,
+    life: f32,
+    color: Color,
+}
+
+pub struct Particles {
+    particles: Vec<Particle>,
+}
+
+const PARTICLE_LIFE: f32 = 0.6;
+const PARTICLE_SIZE: f32 = 3.;
+const PARTICLE_GRAVITY: f32 = 200.;
+
+impl Particles {
+
+    pub fn new() -> Self {
+        Self {
+            particles: Vec::new(),
+        }
+    }
+
+    pub fn burst(&mut self, x: f32, y: f32, count: usize, color: Color) {
+        for _ in 0..count {
+            self.particles.push(Particle {
+                position: vec2(x, y),
+                velocity: vec2(rand::gen_range(-60., 60.), rand::gen_range(-120., -20.)),
+                life: PARTICLE_LIFE,
+                color,
+            });
+        }
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        for particle in &mut self.particles {
+            particle.velocity += vec2(0., PARTICLE_GRAVITY * delta);
+            particle.position += particle.velocity * delta;
+            particle.life -= delta;
+        }
+        self.particles.retain(|particle| particle.life > 0.);
+    }
+
+    // Accelerates the particles inside `region`, e.g. caught in wind.
+    pub fn push(&mut self, region: &AABB<f32>, acceleration: Vec2, delta: f32) {
+        for particle in &mut self.particles {
+            if region.contains_local_point(&na::Point2::new(particle.position.x(), particle.position.y())) {
+                particle.velocity += acceleration * delta;
+            }
+        }
+    }
+
+    pub fn draw(&self) {
+        for particle in &self.particles {
+            draw_rectangle(particle.position.x(), particle.position.y(), PARTICLE_SIZE, PARTICLE_SIZE, particle.color);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use macroquad::*;
+
+// A frame longer than this almost always means the window lost focus or was being dragged, so
+// the game pauses instead of simulating the gap.
+pub const FOCUS_LOSS_DELTA: f32 = 0.5;
+
+pub struct Pause {
+    pub paused: bool,
+    // Whether the pause key was down last frame, so holding it doesn't keep toggling.
+    key_held: bool,
+}
+
+impl Pause {
+
+    pub fn new() -> Self {
+        Self {
+            paused: false,
+            key_held: false,
+        }
+    }
+
+    // Returns the delta the game should run this frame, zero while paused. The long frame that
+    // triggers an auto-pause is dropped, so resuming starts from a normal delta.
+    pub fn update(&mut self, delta: f32, pause_key: bool, auto_pause: bool) -> f32 {
+        if pause_key && !self.key_held {
+            self.paused = !self.paused;
+        }
+        self.key_held = pause_key;
+        if auto_pause && delta > FOCUS_LOSS_DELTA {
+            self.paused = true;
+        }
+        if self.paused { 0. } else { delta }
+    }
+
+    pub fn draw(&self) {
+        if self.paused {
+            draw_text("PAUSED", screen_width() / 2. - 40., screen_height() / 2., 30., BLACK);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1. / 60.;
+
+    #[test]
+    fn long_frame_pauses_and_is_dropped() {
+        let mut pause = Pause::new();
+        assert_eq!(pause.update(DT, false, true), DT);
+        assert_eq!(pause.update(FOCUS_LOSS_DELTA + 1., false, true), 0.);
+        assert!(pause.paused);
+        assert_eq!(pause.update(DT, false, true), 0.);
+        // Resuming runs a normal frame, not the gap.
+        pause.update(DT, true, true);
+        assert_eq!(pause.update(DT, false, true), DT);
+    }
+
+    #[test]
+    fn long_frame_runs_without_auto_pause() {
+        let mut pause = Pause::new();
+        assert_eq!(pause.update(1., false, false), 1.);
+        assert!(!pause.paused);
+    }
+
+    #[test]
+    fn holding_the_key_toggles_once() {
+        let mut pause = Pause::new();
+        pause.update(DT, true, false);
+        pause.update(DT, true, false);
+        assert!(pause.paused);
+        pause.update(DT, false, false);
+        pause.update(DT, true, false);
+        assert!(!pause.paused);
+    }
+}
@@ -0,0 +1,403 @@
+use nalgebra as na;
+use ncollide2d::bounding_volume::{BoundingVolume, AABB};
+use ncollide2d::pipeline::{CollisionGroups, GeometricQueryType};
+use ncollide2d::query::{Proximity, Ray};
+use nphysics2d::object::{DefaultBodySet, DefaultColliderSet, DefaultColliderHandle};
+use nphysics2d::world::DefaultGeometricalWorld;
+
+// Surfaces steeper than this many degrees can't be stood on, bodies slide down them instead.
+pub const SLOPE_LIMIT: f32 = 45.;
+// Contacts flatter than this are walls or ceilings rather than slopes.
+const WALL_NORMAL_Y: f32 = 0.1;
+// More active contacts than this in one step usually means something is spawning out of control
+// or piling up.
+pub const MAX_STEP_CONTACTS: usize = 256;
+// Same predictions nphysics gives colliders built from a default `ColliderDesc`.
+const LINEAR_PREDICTION: f32 = 0.001;
+const ANGULAR_PREDICTION: f32 = std::f32::consts::PI / 180. * 5.;
+// Collision group of enemy ragdolls. Colliders that ignore it pass through them.
+const CORPSE_GROUP: usize = 1;
+
+// Minimum y of a contact normal (pointing down into the surface) for it to count as ground.
+pub fn standing_normal_y() -> f32 {
+    SLOPE_LIMIT.to_radians().cos()
+}
+
+// Normal of the deepest contact between `a` and `b`, oriented from `a` towards `b`.
+pub fn contact_normal(geometrical_world: &DefaultGeometricalWorld<f32>,
+                      colliders: &DefaultColliderSet<f32>,
+                      a: DefaultColliderHandle,
+                      b: DefaultColliderHandle) -> Option<na::Vector2<f32>> {
+    geometrical_world.contacts_with(colliders, a, true)?
+        .find(|(h1, _, h2, _, _, _)| *h1 == b || *h2 == b)
+        .and_then(|(h1, _, _, _, _, manifold)| {
+            let normal = manifold.deepest_contact()?.contact.normal.into_inner();
+            Some(if h1 == a { normal } else { -normal })
+        })
+}
+
+pub fn is_standing_on(geometrical_world: &DefaultGeometricalWorld<f32>,
+                      colliders: &DefaultColliderSet<f32>,
+                      a: DefaultColliderHandle,
+                      surface: DefaultColliderHandle) -> bool {
+    contact_normal(geometrical_world, colliders, a, surface)
+        .is_some_and(|normal| normal.y > standing_normal_y())
+}
+
+// Whether `a` overlaps `sensor`, for zones like ladders that have no solid contacts.
+pub fn is_overlapping(geometrical_world: &DefaultGeometricalWorld<f32>,
+                      colliders: &DefaultColliderSet<f32>,
+                      a: DefaultColliderHandle,
+                      sensor: DefaultColliderHandle) -> bool {
+    geometrical_world.proximity_pair(colliders, a, sensor, true)
+        .is_some_and(|(_, _, _, _, _, proximity)| proximity == Proximity::Intersecting)
+}
+
+// Normals of every contact between `a` and a non-sensor collider, oriented from `a` towards it.
+pub fn surface_normals(geometrical_world: &DefaultGeometricalWorld<f32>,
+                       colliders: &DefaultColliderSet<f32>,
+                       a: DefaultColliderHandle) -> Vec<na::Vector2<f32>> {
+    let contacts = match geometrical_world.contacts_with(colliders, a, true) {
+        Some(contacts) => contacts,
+        None => return Vec::new(),
+    };
+    contacts.filter_map(|(h1, c1, _, c2, _, manifold)| {
+        let other = if h1 == a { c2 } else { c1 };
+        if other.is_sensor() {
+            return None;
+        }
+        let normal = manifold.deepest_contact()?.contact.normal.into_inner();
+        Some(if h1 == a { normal } else { -normal })
+    }).collect()
+}
+
+// Standing on any non-sensor collider that isn't too steep.
+pub fn is_grounded(geometrical_world: &DefaultGeometricalWorld<f32>,
+                   colliders: &DefaultColliderSet<f32>,
+                   a: DefaultColliderHandle) -> bool {
+    surface_normals(geometrical_world, colliders, a).iter().any(|normal| normal.y > standing_normal_y())
+}
+
+// Side of `a` a wall is touching, -1 left or 1 right.
+pub fn wall_side(geometrical_world: &DefaultGeometricalWorld<f32>,
+                 colliders: &DefaultColliderSet<f32>,
+                 a: DefaultColliderHandle) -> Option<f32> {
+    surface_normals(geometrical_world, colliders, a).iter()
+        .find(|normal| normal.y.abs() < WALL_NORMAL_Y)
+        .map(|normal| normal.x.signum())
+}
+
+// Direction down the slope `a` is resting on, if that slope is too steep to stand on.
+pub fn steep_slope_downhill(geometrical_world: &DefaultGeometricalWorld<f32>,
+                            colliders: &DefaultColliderSet<f32>,
+                            a: DefaultColliderHandle) -> Option<na::Vector2<f32>> {
+    let normals = surface_normals(geometrical_world, colliders, a);
+    if normals.iter().any(|normal| normal.y > standing_normal_y()) {
+        return None;
+    }
+    normals.iter()
+        .find(|normal| normal.y > WALL_NORMAL_Y)
+        .map(|normal| {
+            let tangent = na::Vector2::new(-normal.y, normal.x);
+            if tangent.y > 0. { tangent } else { -tangent }
+        })
+}
+
+// Contact points across every colliding pair after the last step.
+pub fn contact_count(geometrical_world: &DefaultGeometricalWorld<f32>, colliders: &DefaultColliderSet<f32>) -> usize {
+    geometrical_world.contact_pairs(colliders, true)
+        .map(|(_, _, _, _, _, manifold)| manifold.len())
+        .sum()
+}
+
+// Union of the bounding boxes of every solid collider on a massless body, which is all the level
+// geometry. None when there isn't any.
+pub fn static_bounds(colliders: &DefaultColliderSet<f32>, bodies: &DefaultBodySet<f32>) -> Option<AABB<f32>> {
+    colliders.iter()
+        .filter(|(_, collider)| !collider.is_sensor())
+        .filter_map(|(_, collider)| bodies.rigid_body(collider.body())
+            .filter(|body| body.augmented_mass().linear == 0.)
+            // From the body rather than `collider.position()`, which only catches up on the first step.
+            .map(|body| collider.shape().aabb(&(body.position() * collider.position_wrt_body()))))
+        .reduce(|bounds, aabb| bounds.merged(&aabb))
+}
+
+pub fn colliders_in_aabb(geometrical_world: &DefaultGeometricalWorld<f32>,
+                         colliders: &DefaultColliderSet<f32>,
+                         aabb: &AABB<f32>) -> Vec<DefaultColliderHandle> {
+    geometrical_world.interferences_with_aabb(colliders, aabb, &CollisionGroups::new())
+        .map(|(handle, _)| handle)
+        .collect()
+}
+
+pub fn collider_at_point(geometrical_world: &DefaultGeometricalWorld<f32>,
+                         colliders: &DefaultColliderSet<f32>,
+                         point: &na::Point2<f32>) -> Option<DefaultColliderHandle> {
+    geometrical_world.interferences_with_point(colliders, point, &CollisionGroups::new())
+        .map(|(handle, _)| handle)
+        .next()
+}
+
+// Switches a collider between solid and sensor in place, keeping its handle. Only for colliders
+// that can't be touching anything yet, like ones just built, see `set_passable`.
+pub fn set_sensor(colliders: &mut DefaultColliderSet<f32>, handle: DefaultColliderHandle, sensor: bool) {
+    let collider = match colliders.get_mut(handle) {
+        Some(collider) => collider,
+        None => return,
+    };
+    if collider.is_sensor() == sensor {
+        return;
+    }
+    let query_type = if sensor {
+        GeometricQueryType::Proximity(LINEAR_PREDICTION)
+    } else {
+        GeometricQueryType::Contacts(collider.margin() + LINEAR_PREDICTION, ANGULAR_PREDICTION)
+    };
+    collider.set_query_type(query_type);
+}
+
+// `set_sensor` for colliders that may already be touching something, like a scripted wall.
+// ncollide keeps a contact pair going when the query type changes under it and panics on the
+// next step, so while passable the collider is also left out of every collision group, which
+// drops its pairs. It doesn't report overlaps either, which nothing toggled this way needs.
+pub fn set_passable(colliders: &mut DefaultColliderSet<f32>, handle: DefaultColliderHandle, passable: bool) {
+    set_sensor(colliders, handle, passable);
+    if let Some(collider) = colliders.get_mut(handle) {
+        let groups = if passable { CollisionGroups::new().with_whitelist(&[]) } else { CollisionGroups::new() };
+        collider.set_collision_groups(groups);
+    }
+}
+
+// Moves a collider into the corpse group.
+pub fn set_corpse(colliders: &mut DefaultColliderSet<f32>, handle: DefaultColliderHandle) {
+    if let Some(collider) = colliders.get_mut(handle) {
+        collider.set_collision_groups(CollisionGroups::new().with_membership(&[CORPSE_GROUP]));
+    }
+}
+
+// Stops a collider from touching ragdolls, which still collide with the level and each other.
+// Whitelisted rather than blacklisted, as the level is in every group including the corpses'
+// and a blacklist would drop the collider through the floor.
+pub fn ignore_corpses(colliders: &mut DefaultColliderSet<f32>, handle: DefaultColliderHandle) {
+    if let Some(collider) = colliders.get_mut(handle) {
+        let mut groups = CollisionGroups::new();
+        groups.modify_whitelist(CORPSE_GROUP, false);
+        collider.set_collision_groups(groups);
+    }
+}
+
+// Rounds of pushing before `depenetration` gives up, e.g. when wedged into a gap narrower than
+// the box.
+const DEPENETRATION_ROUNDS: usize = 4;
+
+// Offset that moves `aabb` out of the bounding boxes of the solid colliders it overlaps, other
+// than `exclude`, each time along the axis that needs the shortest push.
+pub fn depenetration(geometrical_world: &DefaultGeometricalWorld<f32>,
+                     colliders: &DefaultColliderSet<f32>,
+                     aabb: &AABB<f32>,
+                     exclude: DefaultColliderHandle) -> na::Vector2<f32> {
+    let mut offset = na::Vector2::zeros();
+    for _ in 0..DEPENETRATION_ROUNDS {
+        let moved = AABB::new(aabb.mins() + offset, aabb.maxs() + offset);
+        let push = colliders_in_aabb(geometrical_world, colliders, &moved).into_iter()
+            .filter(|handle| *handle != exclude)
+            .filter_map(|handle| colliders.get(handle))
+            .filter(|collider| !collider.is_sensor())
+            .find_map(|collider| shortest_push(&moved, &collider.shape().aabb(collider.position())));
+        match push {
+            Some(push) => offset += push,
+            None => break,
+        }
+    }
+    offset
+}
+
+// Shortest axis-aligned move that takes `a` out of `b`, or None when they don't overlap.
+fn shortest_push(a: &AABB<f32>, b: &AABB<f32>) -> Option<na::Vector2<f32>> {
+    let left = b.mins().x - a.maxs().x;
+    let right = b.maxs().x - a.mins().x;
+    let up = b.mins().y - a.maxs().y;
+    let down = b.maxs().y - a.mins().y;
+    if left >= 0. || right <= 0. || up >= 0. || down <= 0. {
+        return None;
+    }
+    let x = if -left < right { left } else { right };
+    let y = if -up < down { up } else { down };
+    Some(if x.abs() < y.abs() { na::Vector2::new(x, 0.) } else { na::Vector2::new(0., y) })
+}
+
+// Distance from `origin` straight down to the first solid collider, ignoring `exclude`.
+pub fn ground_distance(geometrical_world: &DefaultGeometricalWorld<f32>,
+                       colliders: &DefaultColliderSet<f32>,
+                       origin: na::Point2<f32>,
+                       max_distance: f32,
+                       exclude: DefaultColliderHandle) -> Option<f32> {
+    let ray = Ray::new(origin, na::Vector2::y());
+    geometrical_world.interferences_with_ray(colliders, &ray, max_distance, &CollisionGroups::new())
+        .filter(|(handle, collider, _)| *handle != exclude && !collider.is_sensor())
+        .map(|(_, _, intersection)| intersection.toi)
+        .min_by(|a, b| a.partial_cmp(b).unwrap())
+}
+
+const LEDGE_PROBE_OFFSET: f32 = 20.;
+const LEDGE_PROBE_DEPTH: f32 = 30.;
+
+pub fn is_near_ledge(geometrical_world: &DefaultGeometricalWorld<f32>,
+                     colliders: &DefaultColliderSet<f32>,
+                     position: na::Vector2<f32>,
+                     exclude: DefaultColliderHandle) -> bool {
+    [-LEDGE_PROBE_OFFSET, LEDGE_PROBE_OFFSET].iter().any(|offset| {
+        let origin = na::Point2::new(position.x + offset, position.y);
+        ground_distance(geometrical_world, colliders, origin, LEDGE_PROBE_DEPTH, exclude).is_none()
+    })
+}
+
+// Whether there's no floor just ahead of `position` toward `direction` (1 right, -1 left).
+pub fn is_ledge_ahead(geometrical_world: &DefaultGeometricalWorld<f32>,
+                      colliders: &DefaultColliderSet<f32>,
+                      position: na::Vector2<f32>,
+                      direction: f32,
+                      exclude: DefaultColliderHandle) -> bool {
+    let origin = na::Point2::new(position.x + direction * LEDGE_PROBE_OFFSET, position.y);
+    ground_distance(geometrical_world, colliders, origin, LEDGE_PROBE_DEPTH, exclude).is_none()
+}
+
+// A bare physics world for tests, with the game's gravity and no window or level.
+#[cfg(test)]
+pub mod testing {
+    use nalgebra as na;
+    use nphysics2d::force_generator::DefaultForceGeneratorSet;
+    use nphysics2d::joint::DefaultJointConstraintSet;
+    use nphysics2d::object::{DefaultBodySet, DefaultColliderSet};
+    use nphysics2d::world::{DefaultGeometricalWorld, DefaultMechanicalWorld};
+    use crate::GameObject;
+
+    pub const DT: f32 = 1. / 60.;
+
+    pub struct TestWorld {
+        pub mechanical_world: DefaultMechanicalWorld<f32>,
+        pub geometrical_world: DefaultGeometricalWorld<f32>,
+        pub joint_constraints: DefaultJointConstraintSet<f32>,
+        pub force_generators: DefaultForceGeneratorSet<f32>,
+        pub bodies: DefaultBodySet<f32>,
+        pub colliders: DefaultColliderSet<f32>,
+    }
+
+    impl TestWorld {
+
+        pub fn new() -> Self {
+            let mut mechanical_world = DefaultMechanicalWorld::new(na::Vector2::new(0.0, 9.81));
+            mechanical_world.set_timestep(DT);
+            Self {
+                mechanical_world,
+                geometrical_world: DefaultGeometricalWorld::new(),
+                joint_constraints: DefaultJointConstraintSet::new(),
+                force_generators: DefaultForceGeneratorSet::new(),
+                bodies: DefaultBodySet::new(),
+                colliders: DefaultColliderSet::new(),
+            }
+        }
+
+        // Hero sized mass, like the hero and enemies.
+        pub fn dynamic(&mut self, x: f32, y: f32, width: f32, height: f32) -> GameObject {
+            GameObject::new(x, y, &mut self.bodies, &mut self.colliders, width, height, 75., 1.)
+        }
+
+        // Static, like the level geometry.
+        pub fn fixed(&mut self, x: f32, y: f32, width: f32, height: f32) -> GameObject {
+            GameObject::new(x, y, &mut self.bodies, &mut self.colliders, width, height, 0., 0.)
+        }
+
+        pub fn step(&mut self) {
+            self.mechanical_world.step(
+                &mut self.geometrical_world,
+                &mut self.bodies,
+                &mut self.colliders,
+                &mut self.joint_constraints,
+                &mut self.force_generators,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::testing::TestWorld;
+
+    #[test]
+    fn passable_wall_lets_things_through_and_solid_again_blocks_them() {
+        let mut world = TestWorld::new();
+        let wall = world.fixed(30., 0., 2., 40.);
+        let object = world.dynamic(0., 0., 10., 10.);
+        // Pushed up against the wall first, so the switch happens mid contact.
+        object.set_linear_velocity(&mut world.bodies, na::Vector2::new(40., 0.));
+        for _ in 0..30 {
+            world.step();
+        }
+        assert!(contact_normal(&world.geometrical_world, &world.colliders, object.collider_handle, wall.collider_handle).is_some());
+        set_passable(&mut world.colliders, wall.collider_handle, true);
+        object.set_linear_velocity(&mut world.bodies, na::Vector2::new(40., 0.));
+        for _ in 0..90 {
+            world.step();
+        }
+        assert!(object.position(&world.bodies).x > 50.);
+
+        // Back the other way into the now solid wall.
+        set_passable(&mut world.colliders, wall.collider_handle, false);
+        object.set_linear_velocity(&mut world.bodies, na::Vector2::new(-40., 0.));
+        for _ in 0..90 {
+            world.step();
+        }
+        assert!(object.position(&world.bodies).x > 40.);
+    }
+
+    #[test]
+    fn stacked_boxes_give_a_few_contacts_each() {
+        let mut world = TestWorld::new();
+        world.fixed(0., 100., 100., 5.);
+        for i in 0..4 {
+            world.dynamic(0., 85. - 20. * i as f32, 10., 10.);
+        }
+        for _ in 0..60 {
+            world.step();
+        }
+        // Four resting pairs, each touching along an edge with one or two contact points.
+        let count = contact_count(&world.geometrical_world, &world.colliders);
+        assert!((4..=8).contains(&count), "{} contacts", count);
+        assert!(count <= MAX_STEP_CONTACTS);
+    }
+
+    #[test]
+    fn actors_ignore_corpses_but_not_the_level() {
+        let mut world = TestWorld::new();
+        let ground = world.fixed(0., 100., 200., 5.);
+        let actor = world.dynamic(0., 85., 10., 10.);
+        let corpse = world.dynamic(0., 65., 10., 10.);
+        ignore_corpses(&mut world.colliders, actor.collider_handle);
+        set_corpse(&mut world.colliders, corpse.collider_handle);
+        corpse.set_linear_velocity(&mut world.bodies, na::Vector2::new(0., 30.));
+        for _ in 0..120 {
+            world.step();
+        }
+        assert!(is_standing_on(&world.geometrical_world, &world.colliders, actor.collider_handle, ground.collider_handle));
+        assert!(contact_normal(&world.geometrical_world, &world.colliders, actor.collider_handle, corpse.collider_handle).is_none());
+        // The corpse fell through the actor and landed on the ground too.
+        assert!(is_standing_on(&world.geometrical_world, &world.colliders, corpse.collider_handle, ground.collider_handle));
+    }
+
+    #[test]
+    fn static_bounds_cover_only_the_level_geometry() {
+        let mut world = TestWorld::new();
+        assert!(static_bounds(&world.colliders, &world.bodies).is_none());
+        world.fixed(0., 0., 10., 5.);
+        world.fixed(100., 50., 20., 5.);
+        world.fixed(-40., 80., 5., 30.);
+        // Neither loose bodies nor sensors count.
+        world.dynamic(500., 0., 10., 10.);
+        let sensor = world.fixed(0., -300., 10., 10.);
+        set_sensor(&mut world.colliders, sensor.collider_handle, true);
+        let bounds = static_bounds(&world.colliders, &world.bodies).unwrap();
+        assert_eq!(bounds, AABB::new(na::Point2::new(-45., -6.), na::Point2::new(120., 109.)));
+    }
+}
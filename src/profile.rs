@@ -0,0 +1,152 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const PROFILE_PATH: &str = "profile.cfg";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Ability {
+    DoubleJump,
+    Dash,
+    WallJump,
+    // Pulls nearby coins in.
+    CoinMagnet,
+}
+
+impl Ability {
+
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "double_jump" => Some(Ability::DoubleJump),
+            "dash" => Some(Ability::Dash),
+            "wall_jump" => Some(Ability::WallJump),
+            "coin_magnet" => Some(Ability::CoinMagnet),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Ability::DoubleJump => "double_jump",
+            Ability::Dash => "dash",
+            Ability::WallJump => "wall_jump",
+            Ability::CoinMagnet => "coin_magnet",
+        }
+    }
+}
+
+// Progress across levels, kept apart from the per-level quick save. Same `key = value` layout as
+// the settings file, with sets written as comma separated lists.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub unlocked_levels: BTreeSet<u32>,
+    pub completed_levels: BTreeSet<u32>,
+    pub total_coins: u32,
+    pub abilities: BTreeSet<Ability>,
+}
+
+// A new profile can only play the first level.
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            unlocked_levels: [0].iter().copied().collect(),
+            completed_levels: BTreeSet::new(),
+            total_coins: 0,
+            abilities: BTreeSet::new(),
+        }
+    }
+}
+
+impl Profile {
+
+    // Marks `level` complete and unlocks the one after it.
+    pub fn complete_level(&mut self, level: u32) {
+        self.completed_levels.insert(level);
+        self.unlocked_levels.insert(level + 1);
+    }
+
+    pub fn has_ability(&self, ability: Ability) -> bool {
+        self.abilities.contains(&ability)
+    }
+
+    pub fn unlock_ability(&mut self, ability: Ability) {
+        self.abilities.insert(ability);
+    }
+
+    // Unknown keys and unparsable entries are ignored, like in the settings file.
+    pub fn parse(text: &str) -> Self {
+        let mut profile = Self::default();
+        for line in text.lines() {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            let items = value.split(',').map(str::trim).filter(|item| !item.is_empty());
+            match key {
+                "unlocked_levels" => profile.unlocked_levels.extend(items.filter_map(|item| item.parse::<u32>().ok())),
+                "completed_levels" => profile.completed_levels.extend(items.filter_map(|item| item.parse::<u32>().ok())),
+                "abilities" => profile.abilities.extend(items.filter_map(Ability::parse)),
+                "total_coins" => {
+                    if let Ok(value) = value.parse() {
+                        profile.total_coins = value;
+                    }
+                }
+                _ => {}
+            }
+        }
+        profile
+    }
+
+    pub fn to_text(&self) -> String {
+        let list = |items: Vec<String>| items.join(", ");
+        format!("unlocked_levels = {}\ncompleted_levels = {}\ntotal_coins = {}\nabilities = {}\n",
+                list(self.unlocked_levels.iter().map(u32::to_string).collect()),
+                list(self.completed_levels.iter().map(u32::to_string).collect()),
+                self.total_coins,
+                list(self.abilities.iter().map(|ability| ability.name().to_string()).collect()))
+    }
+
+    // A missing file is a fresh profile.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path.as_ref()) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completing_a_level_unlocks_the_next() {
+        let mut profile = Profile::default();
+        assert!(!profile.unlocked_levels.contains(&1));
+        profile.complete_level(0);
+        assert!(profile.completed_levels.contains(&0));
+        assert!(profile.unlocked_levels.contains(&1));
+        assert!(!profile.completed_levels.contains(&1));
+    }
+
+    #[test]
+    fn profile_round_trips_through_the_file() {
+        let path = std::env::temp_dir().join("new_game_profile_test.cfg");
+        let mut profile = Profile::default();
+        profile.complete_level(0);
+        profile.complete_level(1);
+        profile.total_coins = 42;
+        profile.unlock_ability(Ability::Dash);
+        profile.unlock_ability(Ability::WallJump);
+        profile.save(&path).unwrap();
+        let loaded = Profile::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, profile);
+        assert_eq!(Profile::load(&path).unwrap(), Profile::default());
+    }
+}
@@ -0,0 +1,117 @@
+use macroquad::*;
+use nalgebra as na;
+use ncollide2d::bounding_volume::AABB;
+use nphysics2d::object::DefaultColliderHandle;
+
+const PROJECTILE_SIZE: f32 = 4.;
+const PROJECTILE_LIFE: f32 = 3.;
+
+pub struct Projectile {
+    pub position: na::Vector2<f32>,
+    pub velocity: na::Vector2<f32>,
+    pub damage: u32,
+    pub life: f32,
+    // Fired by the hero, so it hurts enemies instead of him.
+    pub friendly: bool,
+    // Targets left to hit before the projectile is spent.
+    pub pierce: u8,
+    // Everything already hit, so a piercing projectile doesn't hit the same target every tick.
+    hits: Vec<DefaultColliderHandle>,
+}
+
+impl Projectile {
+
+    pub fn new(position: na::Vector2<f32>, velocity: na::Vector2<f32>, damage: u32) -> Self {
+        Self {
+            position,
+            velocity,
+            damage,
+            life: PROJECTILE_LIFE,
+            friendly: false,
+            pierce: 1,
+            hits: Vec::new(),
+        }
+    }
+
+    pub fn friendly(mut self, pierce: u8) -> Self {
+        self.friendly = true;
+        self.pierce = pierce.max(1);
+        self
+    }
+
+    // Registers a hit on `target` and returns whether it should take damage, which it doesn't if
+    // this projectile already went through it.
+    pub fn strike(&mut self, target: DefaultColliderHandle) -> bool {
+        if self.is_expired() || self.hits.contains(&target) {
+            return false;
+        }
+        self.hits.push(target);
+        self.pierce -= 1;
+        if self.pierce == 0 {
+            self.life = 0.;
+        }
+        true
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.position += self.velocity * delta;
+        self.life -= delta;
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.life <= 0.
+    }
+
+    pub fn aabb(&self) -> AABB<f32> {
+        let half_extents = na::Vector2::new(PROJECTILE_SIZE, PROJECTILE_SIZE);
+        let center = na::Point2::from(self.position);
+        AABB::new(center - half_extents, center + half_extents)
+    }
+
+    pub fn draw(&self) {
+        draw_circle(self.position.x, self.position.y, PROJECTILE_SIZE, if self.friendly { SKYBLUE } else { ORANGE });
+    }
+}
+
+// Unit vector from `from` towards `to`, none if they are on top of each other.
+pub fn aim_direction(from: na::Vector2<f32>, to: na::Vector2<f32>) -> Option<na::Vector2<f32>> {
+    let offset = to - from;
+    let length = offset.norm();
+    if length > f32::EPSILON { Some(offset / length) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{self, testing::{TestWorld, DT}};
+
+    #[test]
+    fn piercing_shot_hits_the_first_two_in_line_then_is_spent() {
+        let mut world = TestWorld::new();
+        let targets: Vec<_> = [40., 80., 120.].iter().map(|&x| world.fixed(x, 0., 5., 10.)).collect();
+        world.step();
+        let mut projectile = Projectile::new(na::Vector2::new(0., 0.), na::Vector2::new(300., 0.), 1).friendly(2);
+        let mut struck = Vec::new();
+        for _ in 0..60 {
+            projectile.update(DT);
+            let hit = physics::colliders_in_aabb(&world.geometrical_world, &world.colliders, &projectile.aabb());
+            for (index, target) in targets.iter().enumerate() {
+                if hit.contains(&target.collider_handle) && projectile.strike(target.collider_handle) {
+                    struck.push(index);
+                }
+            }
+            if projectile.is_expired() {
+                break;
+            }
+        }
+        assert_eq!(struck, vec![0, 1]);
+        assert!(projectile.is_expired());
+    }
+
+    #[test]
+    fn aim_direction_points_at_the_cursor() {
+        let direction = aim_direction(na::Vector2::new(100., 200.), na::Vector2::new(130., 160.)).unwrap();
+        assert!((direction - na::Vector2::new(0.6, -0.8)).norm() < 1e-6, "aiming along {:?}", direction);
+        assert_eq!(aim_direction(na::Vector2::new(100., 200.), na::Vector2::new(100., 200.)), None);
+    }
+}
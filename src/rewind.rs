@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+use nalgebra as na;
+use nphysics2d::algebra::Velocity2;
+use nphysics2d::object::{DefaultBodySet, DefaultBodyHandle};
+
+// About five seconds at 60 fps.
+pub const REWIND_CAPACITY: usize = 300;
+
+struct BodySnapshot {
+    handle: DefaultBodyHandle,
+    position: na::Isometry2<f32>,
+    velocity: Velocity2<f32>,
+}
+
+// Ring buffer of per-step body states, oldest snapshots are dropped once it's full.
+pub struct Rewind {
+    snapshots: VecDeque<Vec<BodySnapshot>>,
+    capacity: usize,
+}
+
+impl Rewind {
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, bodies: &DefaultBodySet<f32>) {
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        let snapshot = bodies.iter()
+            .filter_map(|(handle, _)| bodies.rigid_body(handle).map(|body| BodySnapshot {
+                handle,
+                position: *body.position(),
+                velocity: *body.velocity(),
+            }))
+            .collect();
+        self.snapshots.push_back(snapshot);
+    }
+
+    // Restores the most recent snapshot, returns false once there's nothing left to rewind.
+    // Bodies removed since the snapshot was taken are skipped.
+    pub fn rewind(&mut self, bodies: &mut DefaultBodySet<f32>) -> bool {
+        let snapshot = match self.snapshots.pop_back() {
+            Some(snapshot) => snapshot,
+            None => return false,
+        };
+        for body_snapshot in snapshot {
+            if let Some(body) = bodies.rigid_body_mut(body_snapshot.handle) {
+                body.set_position(body_snapshot.position);
+                body.set_velocity(body_snapshot.velocity);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::TestWorld;
+
+    #[test]
+    fn rewind_restores_earlier_states_exactly() {
+        let mut world = TestWorld::new();
+        let object = world.dynamic(0., 0., 10., 10.);
+        object.set_linear_velocity(&mut world.bodies, na::Vector2::new(30., -20.));
+        let mut rewind = Rewind::new(10);
+        let mut states = Vec::new();
+        for _ in 0..5 {
+            world.step();
+            rewind.record(&world.bodies);
+            let body = object.rigid_body(&world.bodies);
+            states.push((*body.position(), *body.velocity()));
+        }
+        for _ in 0..3 {
+            world.step();
+        }
+        for (position, velocity) in states.iter().rev() {
+            assert!(rewind.rewind(&mut world.bodies));
+            let body = object.rigid_body(&world.bodies);
+            assert_eq!(body.position(), position);
+            assert_eq!(body.velocity().linear, velocity.linear);
+            assert_eq!(body.velocity().angular, velocity.angular);
+        }
+        assert!(!rewind.rewind(&mut world.bodies));
+    }
+
+    #[test]
+    fn buffer_keeps_only_the_latest_snapshots() {
+        let mut world = TestWorld::new();
+        world.dynamic(0., 0., 10., 10.);
+        let mut rewind = Rewind::new(3);
+        for _ in 0..10 {
+            world.step();
+            rewind.record(&world.bodies);
+        }
+        assert_eq!(rewind.snapshots.len(), 3);
+        for _ in 0..3 {
+            assert!(rewind.rewind(&mut world.bodies));
+        }
+        assert!(!rewind.rewind(&mut world.bodies));
+    }
+}
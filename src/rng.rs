@@ -0,0 +1,37 @@
+// Small xorshift generator for gameplay rolls. Unlike macroquad's global generator it belongs to
+// the world and is seeded from the level, so the same inputs always roll the same numbers.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+
+    // Xorshift never leaves zero, so a zero seed is bumped to one.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed.max(1),
+        }
+    }
+
+    // Where the sequence is at, enough to pick it up again from here.
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    // Uniform in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // True with `probability`, always for 1 and never for 0.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
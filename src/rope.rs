@@ -0,0 +1,152 @@
+use macroquad::*;
+use nalgebra as na;
+use nphysics2d::joint::{DefaultJointConstraintHandle, DefaultJointConstraintSet, RevoluteConstraint};
+use nphysics2d::math::Velocity;
+use nphysics2d::object::{BodyPartHandle, BodyStatus, DefaultBodyHandle, DefaultBodySet, RigidBodyDesc};
+use crate::GameObject;
+
+// How far from the hanging rope the hero's centre can be and still catch it.
+pub const ROPE_REACH: f32 = 12.;
+// The link between the anchor and the hero turns about its own centre, so its mass never
+// slows the swing. A link much lighter than the hero lets the joints stretch and the hero drift
+// off the rope, so it is kept within a few times his mass, and its inertia small next to his.
+const LINK_MASS: f32 = 100.;
+const LINK_INERTIA: f32 = 1000.;
+
+// A rope or chain hanging straight down from a fixed point.
+pub struct Rope {
+    pub anchor: na::Vector2<f32>,
+    pub length: f32,
+    anchor_body: DefaultBodyHandle,
+}
+
+impl Rope {
+
+    pub fn new(x: f32, y: f32, length: f32, bodies: &mut DefaultBodySet<f32>) -> Self {
+        let anchor = na::Vector2::new(x, y);
+        let body = RigidBodyDesc::new().translation(anchor).status(BodyStatus::Static).build();
+        Self {
+            anchor,
+            length,
+            anchor_body: bodies.insert(body),
+        }
+    }
+
+    // Whether `position` is close enough to the rope, at rest, to grab it.
+    pub fn can_reach(&self, position: na::Vector2<f32>) -> bool {
+        let below = (position.y - self.anchor.y).clamp(0., self.length);
+        (position - (self.anchor + na::Vector2::new(0., below))).norm() <= ROPE_REACH
+    }
+
+    pub fn draw(&self, end: Option<na::Vector2<f32>>) {
+        let end = end.unwrap_or(self.anchor + na::Vector2::new(0., self.length));
+        draw_circle(self.anchor.x, self.anchor.y, 3., DARKGRAY);
+        draw_line(self.anchor.x, self.anchor.y, end.x, end.y, 2., BROWN);
+    }
+}
+
+struct Swing {
+    rope: usize,
+    link: DefaultBodyHandle,
+    joints: [DefaultJointConstraintHandle; 2],
+}
+
+// The hero hanging from a rope. A light link body swings around the anchor on one revolute
+// joint and holds the hero at its far end on another, so the hero's own rotation never
+// matters.
+pub struct RopeGrab {
+    swing: Option<Swing>,
+}
+
+impl RopeGrab {
+
+    pub fn new() -> Self {
+        Self {
+            swing: None,
+        }
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.swing.is_some()
+    }
+
+    // Index of the rope being swung on.
+    pub fn rope(&self) -> Option<usize> {
+        self.swing.as_ref().map(|swing| swing.rope)
+    }
+
+    // Hangs `hero` from `ropes[index]` where he is, keeping the part of his velocity across
+    // the rope as the starting swing.
+    pub fn attach(&mut self, bodies: &mut DefaultBodySet<f32>, joints: &mut DefaultJointConstraintSet<f32>, ropes: &[Rope], index: usize, hero: &GameObject) {
+        if self.is_attached() {
+            return;
+        }
+        let rope = &ropes[index];
+        let offset = hero.position(bodies) - rope.anchor;
+        let length = offset.norm().max(f32::EPSILON);
+        let velocity = hero.linear_velocity(bodies);
+        // The link's local +y axis points at the hero, and it turns as fast as he moves around
+        // the anchor.
+        let angle = (-offset.x).atan2(offset.y);
+        let angular_velocity = (offset.x * velocity.y - offset.y * velocity.x) / (length * length);
+        let link = RigidBodyDesc::new()
+            .translation(rope.anchor)
+            .rotation(angle)
+            .velocity(Velocity::new(na::zero(), angular_velocity))
+            .mass(LINK_MASS)
+            .angular_inertia(LINK_INERTIA)
+            .gravity_enabled(false)
+            .build();
+        let link = bodies.insert(link);
+        let pivot = RevoluteConstraint::new(BodyPartHandle(rope.anchor_body, 0), BodyPartHandle(link, 0), na::Point2::origin(), na::Point2::origin());
+        let hold = RevoluteConstraint::new(BodyPartHandle(link, 0), BodyPartHandle(hero.body_handle, 0), na::Point2::new(0., length), na::Point2::origin());
+        self.swing = Some(Swing {
+            rope: index,
+            link,
+            joints: [joints.insert(pivot), joints.insert(hold)],
+        });
+    }
+
+    // Lets go of the rope. The hero keeps whatever velocity the swing gave him.
+    pub fn detach(&mut self, bodies: &mut DefaultBodySet<f32>, joints: &mut DefaultJointConstraintSet<f32>) {
+        let swing = match self.swing.take() {
+            Some(swing) => swing,
+            None => return,
+        };
+        for joint in &swing.joints {
+            joints.remove(*joint);
+        }
+        bodies.remove(swing.link);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::TestWorld;
+
+    #[test]
+    fn letting_go_keeps_the_swing_velocity() {
+        let mut world = TestWorld::new();
+        let ropes = vec![Rope::new(0., 0., 70., &mut world.bodies)];
+        let hero = world.dynamic(0., 60., 10., 10.);
+        hero.set_linear_velocity(&mut world.bodies, na::Vector2::new(40., 0.));
+        let mut grab = RopeGrab::new();
+        grab.attach(&mut world.bodies, &mut world.joint_constraints, &ropes, 0, &hero);
+        for _ in 0..30 {
+            world.step();
+        }
+        let offset = hero.position(&world.bodies) - ropes[0].anchor;
+        let velocity = hero.linear_velocity(&world.bodies);
+        assert!((offset.norm() - 60.).abs() < 1., "{} from the anchor", offset.norm());
+        assert!(offset.x > 10., "didn't swing, at {:?}", offset);
+        // Moving along the arc, not toward or away from the anchor.
+        assert!(velocity.dot(&offset).abs() / (offset.norm() * velocity.norm()) < 0.1, "{:?} isn't across {:?}", velocity, offset);
+
+        grab.detach(&mut world.bodies, &mut world.joint_constraints);
+        assert!(!grab.is_attached());
+        world.step();
+        let released = hero.linear_velocity(&world.bodies);
+        assert!((released - velocity).norm() < 1., "{:?} after letting go, {:?} before", released, velocity);
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const SAVE_PATH: &str = "save.cfg";
+pub const SAVE_VERSION: u32 = 2;
+
+// Raw `key = value` pairs of a save file, before they are checked against the current schema.
+type Fields = HashMap<String, String>;
+
+// MIGRATIONS[n] upgrades a version n + 1 save to version n + 2, so a save of any older version
+// is brought up to date by running every migration from its version onwards.
+const MIGRATIONS: [fn(&mut Fields); 1] = [migrate_v1_to_v2];
+
+// Version 1 predates the boss gate, so the boss can't have been beaten yet.
+fn migrate_v1_to_v2(fields: &mut Fields) {
+    fields.insert("boss_defeated".to_string(), "false".to_string());
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    MissingVersion,
+    // Written by a newer build than this one, or just garbage.
+    UnsupportedVersion(u32),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "{}", error),
+            SaveError::MissingVersion => write!(f, "save has no version"),
+            SaveError::UnsupportedVersion(version) => write!(f, "unsupported save version {}", version),
+            SaveError::Invalid(key) => write!(f, "missing or invalid `{}`", key),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+// Same plain `key = value` layout as the settings file, with the schema version on top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveGame {
+    pub hero_x: f32,
+    pub hero_y: f32,
+    pub hero_health: u32,
+    pub boss_defeated: bool,
+}
+
+impl SaveGame {
+
+    pub fn parse(text: &str) -> Result<Self, SaveError> {
+        let mut fields = Fields::new();
+        for line in text.lines() {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            if !key.is_empty() {
+                fields.insert(key.to_string(), value.to_string());
+            }
+        }
+        let version = fields.get("version").ok_or(SaveError::MissingVersion)?;
+        let version = version.parse::<u32>().map_err(|_| SaveError::Invalid("version"))?;
+        if version == 0 || version > SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(version));
+        }
+        for migrate in &MIGRATIONS[version as usize - 1..] {
+            migrate(&mut fields);
+        }
+        Ok(Self {
+            hero_x: field(&fields, "hero_x")?,
+            hero_y: field(&fields, "hero_y")?,
+            hero_health: field(&fields, "hero_health")?,
+            boss_defeated: field(&fields, "boss_defeated")?,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        format!("version = {}\nhero_x = {}\nhero_y = {}\nhero_health = {}\nboss_defeated = {}\n",
+                SAVE_VERSION, self.hero_x, self.hero_y, self.hero_health, self.boss_defeated)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+fn field<T: std::str::FromStr>(fields: &Fields, key: &'static str) -> Result<T, SaveError> {
+    fields.get(key)
+        .and_then(|value| value.parse().ok())
+        .ok_or(SaveError::Invalid(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v1_save_is_migrated_to_v2() {
+        let save = SaveGame::parse("version = 1\nhero_x = 12.5\nhero_y = 300\nhero_health = 2\n").unwrap();
+        assert_eq!(save, SaveGame { hero_x: 12.5, hero_y: 300., hero_health: 2, boss_defeated: false });
+    }
+
+    #[test]
+    fn current_save_round_trips() {
+        let save = SaveGame { hero_x: -4., hero_y: 455.25, hero_health: 3, boss_defeated: true };
+        assert_eq!(SaveGame::parse(&save.to_text()).unwrap(), save);
+    }
+
+    #[test]
+    fn unknown_versions_and_bad_fields_are_errors() {
+        assert!(matches!(SaveGame::parse("version = 3\n"), Err(SaveError::UnsupportedVersion(3))));
+        assert!(matches!(SaveGame::parse("version = 0\n"), Err(SaveError::UnsupportedVersion(0))));
+        assert!(matches!(SaveGame::parse("hero_x = 1\n"), Err(SaveError::MissingVersion)));
+        assert!(matches!(SaveGame::parse("version = two\n"), Err(SaveError::Invalid("version"))));
+        assert!(matches!(SaveGame::parse("version = 2\nhero_x = 1\nhero_y = 1\nhero_health = full\nboss_defeated = false\n"),
+                         Err(SaveError::Invalid("hero_health"))));
+    }
+}
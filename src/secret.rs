@@ -0,0 +1,96 @@
+use macroquad::*;
+use nalgebra as na;
+
+// How close the hero has to get before the wall starts fading.
+const REVEAL_MARGIN: f32 = 20.;
+const FADE_SPEED: f32 = 2.;
+
+// A purely visual cover over a hidden passage, it has no collider.
+pub struct SecretWall {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+    pub alpha: f32,
+    pub revealed: bool,
+    // Fade back in once the hero leaves instead of staying revealed.
+    pub rehide: bool,
+}
+
+impl SecretWall {
+
+    pub fn new(x: f32, y: f32, w: f32, h: f32, rehide: bool) -> Self {
+        Self {
+            x,
+            y,
+            w,
+            h,
+            alpha: 1.,
+            revealed: false,
+            rehide,
+        }
+    }
+
+    pub fn is_near(&self, position: na::Vector2<f32>) -> bool {
+        position.x > self.x - REVEAL_MARGIN && position.x < self.x + self.w + REVEAL_MARGIN
+            && position.y > self.y - REVEAL_MARGIN && position.y < self.y + self.h + REVEAL_MARGIN
+    }
+
+    pub fn update(&mut self, delta: f32, hero: na::Vector2<f32>) {
+        let near = self.is_near(hero);
+        if near {
+            self.revealed = true;
+        } else if self.rehide {
+            self.revealed = false;
+        }
+        let target = if self.revealed { 0. } else { 1. };
+        let step = FADE_SPEED * delta;
+        self.alpha = if self.alpha < target { (self.alpha + step).min(target) } else { (self.alpha - step).max(target) };
+    }
+
+    pub fn draw(&self) {
+        if self.alpha > 0. {
+            draw_rectangle(self.x, self.y, self.w, self.h, Color([80, 63, 47, (self.alpha * 255.) as u8]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1. / 60.;
+
+    #[test]
+    fn wall_fades_out_with_the_hero_inside() {
+        let mut wall = SecretWall::new(0., 0., 60., 40., false);
+        let inside = na::Vector2::new(30., 20.);
+        wall.update(DT, inside);
+        assert!(wall.alpha < 1.);
+        for _ in 0..60 {
+            wall.update(DT, inside);
+        }
+        assert_eq!(wall.alpha, 0.);
+    }
+
+    #[test]
+    fn revealed_wall_stays_revealed_unless_it_rehides() {
+        let away = na::Vector2::new(500., 500.);
+        for &rehide in &[false, true] {
+            let mut wall = SecretWall::new(0., 0., 60., 40., rehide);
+            wall.update(DT, na::Vector2::new(-10., 20.));
+            for _ in 0..60 {
+                wall.update(DT, away);
+            }
+            assert_eq!(wall.alpha, if rehide { 1. } else { 0. });
+        }
+    }
+
+    #[test]
+    fn far_away_hero_leaves_the_wall_opaque() {
+        let mut wall = SecretWall::new(0., 0., 60., 40., false);
+        wall.update(DT, na::Vector2::new(-REVEAL_MARGIN - 1., 20.));
+        assert_eq!(wall.alpha, 1.);
+        assert!(!wall.revealed);
+    }
+}
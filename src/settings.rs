@@ -0,0 +1,234 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+pub const SETTINGS_PATH: &str = "settings.cfg";
+// Size of the view in world pixels that an automatic render scale fits to the window.
+pub const VIRTUAL_WIDTH: f32 = 400.;
+pub const VIRTUAL_HEIGHT: f32 = 300.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    // Multipliers applied to enemies' base values when they spawn.
+    pub fn health_multiplier(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.5,
+        }
+    }
+
+    pub fn damage_multiplier(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 2.,
+        }
+    }
+}
+
+// Rounded, but never scaled all the way down to nothing.
+pub fn scale_stat(value: u32, multiplier: f32) -> u32 {
+    ((value as f32 * multiplier).round() as u32).max(1)
+}
+
+// `true` or `false`, anything else leaves `setting` as it was.
+fn parse_bool(value: &str, setting: &mut bool) {
+    if let Ok(value) = value.parse() {
+        *setting = value;
+    }
+}
+
+// Stored as plain `key = value` lines so the file is easy to edit by hand.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserSettings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub zoom: f32,
+    // Multiplies jumps, dashes, knockback and bounces all at once, for tuning how the game feels.
+    pub impulse_scale: f32,
+    // Whole screen pixels per world pixel, or 0 to pick the largest that fits `VIRTUAL_WIDTH` by
+    // `VIRTUAL_HEIGHT` in the window.
+    pub render_scale: u32,
+    // Sprite set to load, 2 for the @2x assets on high density displays. Any missing @2x sprite
+    // falls back to its @1x one.
+    pub asset_scale: u32,
+    // Skip slow motion and similar screen effects.
+    pub reduce_motion: bool,
+    pub pixel_snap: bool,
+    // A blob on the ground under the hero that shrinks and fades the higher he is.
+    pub hero_shadow: bool,
+    // Pause by itself after a very long frame, e.g. when the window lost focus.
+    pub auto_pause: bool,
+    // Shoot and throw toward the mouse cursor instead of the way the hero faces.
+    pub mouse_aim: bool,
+    // Defeated enemies tumble away as physics bodies before fading, instead of just fading.
+    pub enemy_ragdolls: bool,
+    // Holding attack keeps swinging, advancing combos, as soon as each swing allows the next.
+    pub auto_attack: bool,
+    // Show which actions are held, for streaming and speedruns.
+    pub input_display: bool,
+    // Ticks every input is held back before the game sees it.
+    pub input_delay: u32,
+    pub difficulty: Difficulty,
+    // Pausing also appends a debug snapshot of the world to `SNAPSHOT_PATH`.
+    pub pause_snapshot: bool,
+    // Debug builds only: step a shadow world next to the real one and report when they differ.
+    pub determinism_check: bool,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.,
+            music_volume: 0.8,
+            sfx_volume: 0.8,
+            zoom: 1.,
+            impulse_scale: 1.,
+            render_scale: 1,
+            asset_scale: 1,
+            reduce_motion: false,
+            pixel_snap: true,
+            hero_shadow: true,
+            auto_pause: true,
+            mouse_aim: false,
+            enemy_ragdolls: false,
+            auto_attack: false,
+            input_display: false,
+            input_delay: 0,
+            difficulty: Difficulty::Normal,
+            pause_snapshot: false,
+            determinism_check: false,
+        }
+    }
+}
+
+impl UserSettings {
+
+    // Unknown keys and unparsable values are ignored and keep their defaults.
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Self::default();
+        for line in text.lines() {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            match (key, value.parse::<f32>().ok()) {
+                ("master_volume", Some(number)) => settings.master_volume = number.clamp(0., 1.),
+                ("music_volume", Some(number)) => settings.music_volume = number.clamp(0., 1.),
+                ("sfx_volume", Some(number)) => settings.sfx_volume = number.clamp(0., 1.),
+                ("zoom", Some(number)) if number > 0. => settings.zoom = number,
+                ("impulse_scale", Some(number)) if number > 0. => settings.impulse_scale = number,
+                ("render_scale", _) => settings.render_scale = value.parse().unwrap_or(settings.render_scale),
+                ("asset_scale", _) => settings.asset_scale = value.parse::<u32>().map_or(settings.asset_scale, |scale| scale.max(1)),
+                ("input_delay", _) => settings.input_delay = value.parse().unwrap_or(settings.input_delay),
+                ("difficulty", _) => settings.difficulty = Difficulty::parse(value).unwrap_or(settings.difficulty),
+                ("reduce_motion", _) => parse_bool(value, &mut settings.reduce_motion),
+                ("pixel_snap", _) => parse_bool(value, &mut settings.pixel_snap),
+                ("hero_shadow", _) => parse_bool(value, &mut settings.hero_shadow),
+                ("auto_pause", _) => parse_bool(value, &mut settings.auto_pause),
+                ("mouse_aim", _) => parse_bool(value, &mut settings.mouse_aim),
+                ("enemy_ragdolls", _) => parse_bool(value, &mut settings.enemy_ragdolls),
+                ("auto_attack", _) => parse_bool(value, &mut settings.auto_attack),
+                ("input_display", _) => parse_bool(value, &mut settings.input_display),
+                ("pause_snapshot", _) => parse_bool(value, &mut settings.pause_snapshot),
+                ("determinism_check", _) => parse_bool(value, &mut settings.determinism_check),
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    pub fn to_text(&self) -> String {
+        format!("master_volume = {}\nmusic_volume = {}\nsfx_volume = {}\nzoom = {}\nimpulse_scale = {}\nrender_scale = {}\nasset_scale = {}\nreduce_motion = {}\npixel_snap = {}\nhero_shadow = {}\nauto_pause = {}\nmouse_aim = {}\nenemy_ragdolls = {}\nauto_attack = {}\ninput_display = {}\ninput_delay = {}\ndifficulty = {}\npause_snapshot = {}\ndeterminism_check = {}\n",
+                self.master_volume, self.music_volume, self.sfx_volume, self.zoom, self.impulse_scale, self.render_scale, self.asset_scale, self.reduce_motion, self.pixel_snap, self.hero_shadow, self.auto_pause,
+                self.mouse_aim, self.enemy_ragdolls, self.auto_attack, self.input_display, self.input_delay, self.difficulty.name(),
+                self.pause_snapshot, self.determinism_check)
+    }
+
+    // A missing file is created with the defaults.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path.as_ref()) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                let settings = Self::default();
+                settings.save(path)?;
+                Ok(settings)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    // When the file at `path` was last written, to notice it being edited while the game runs.
+    pub fn modified(path: impl AsRef<Path>) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_kind_of_value() {
+        let settings = UserSettings::parse("zoom = 2\nreduce_motion = true\npixel_snap=false\nasset_scale = 2\ndifficulty = hard\npause_snapshot = true\n");
+        assert_eq!(settings.zoom, 2.);
+        assert!(settings.reduce_motion);
+        assert!(!settings.pixel_snap);
+        assert_eq!(settings.asset_scale, 2);
+        assert_eq!(settings.difficulty, Difficulty::Hard);
+        assert!(settings.pause_snapshot);
+    }
+
+    #[test]
+    fn bad_values_keep_the_defaults() {
+        let settings = UserSettings::parse("zoom = -1\nreduce_motion = yes\nhero_shadow = 1\nasset_scale = 0\ndifficulty = brutal\nunknown = true\nmaster_volume\n");
+        assert_eq!(settings, UserSettings { asset_scale: 1, ..UserSettings::default() });
+    }
+
+    #[test]
+    fn scaled_stats_round_but_never_reach_zero() {
+        assert_eq!(scale_stat(4, 1.5), 6);
+        assert_eq!(scale_stat(3, 0.5), 2);
+        assert_eq!(scale_stat(1, 0.5), 1);
+    }
+
+    #[test]
+    fn missing_file_is_written_with_the_defaults() {
+        let path = std::env::temp_dir().join("new_game_missing_settings_test.cfg");
+        let _ = fs::remove_file(&path);
+        assert_eq!(UserSettings::load(&path).unwrap(), UserSettings::default());
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(UserSettings::parse(&written), UserSettings::default());
+    }
+}
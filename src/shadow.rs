@@ -0,0 +1,58 @@
+use macroquad::*;
+
+// Past this height above the ground the hero casts no shadow.
+pub const SHADOW_MAX_HEIGHT: f32 = 120.;
+const SHADOW_WIDTH: f32 = 18.;
+const SHADOW_HEIGHT: f32 = 4.;
+// Share of the full size left at the maximum height.
+const SHADOW_MIN_SCALE: f32 = 0.3;
+const SHADOW_ALPHA: f32 = 0.45;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowBlob {
+    pub width: f32,
+    pub height: f32,
+    pub alpha: f32,
+}
+
+// The blob under something `height` above the ground: full size and darkest when touching it,
+// shrinking and fading out toward `SHADOW_MAX_HEIGHT`.
+pub fn shadow_blob(height: f32) -> ShadowBlob {
+    let t = (height / SHADOW_MAX_HEIGHT).clamp(0., 1.);
+    let scale = 1. - t * (1. - SHADOW_MIN_SCALE);
+    ShadowBlob {
+        width: SHADOW_WIDTH * scale,
+        height: SHADOW_HEIGHT * scale,
+        alpha: SHADOW_ALPHA * (1. - t),
+    }
+}
+
+// Centred on `x`, resting on the ground at `ground_y`. Round caps on a flat box stand in for an
+// ellipse.
+pub fn draw_shadow(x: f32, ground_y: f32, blob: &ShadowBlob) {
+    let color = Color([0, 0, 0, (255. * blob.alpha) as u8]);
+    let radius = blob.height / 2.;
+    let y = ground_y - radius;
+    draw_rectangle(x - blob.width / 2. + radius, ground_y - blob.height, blob.width - blob.height, blob.height, color);
+    draw_circle(x - blob.width / 2. + radius, y, radius, color);
+    draw_circle(x + blob.width / 2. - radius, y, radius, color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shadow_shrinks_and_fades_with_height() {
+        assert_eq!(shadow_blob(0.), ShadowBlob { width: SHADOW_WIDTH, height: SHADOW_HEIGHT, alpha: SHADOW_ALPHA });
+        let half = shadow_blob(SHADOW_MAX_HEIGHT / 2.);
+        assert!((half.width - SHADOW_WIDTH * 0.65).abs() < 1e-4);
+        assert!((half.alpha - SHADOW_ALPHA / 2.).abs() < 1e-4);
+        let top = shadow_blob(SHADOW_MAX_HEIGHT);
+        assert!((top.width - SHADOW_WIDTH * SHADOW_MIN_SCALE).abs() < 1e-4);
+        assert_eq!(top.alpha, 0.);
+        // Clamped both ways.
+        assert_eq!(shadow_blob(SHADOW_MAX_HEIGHT * 3.), top);
+        assert_eq!(shadow_blob(-5.), shadow_blob(0.));
+    }
+}
@@ -0,0 +1,73 @@
+use macroquad::*;
+use nalgebra as na;
+use nphysics2d::object::{DefaultBodySet, DefaultColliderSet};
+use crate::GameObject;
+
+const SLOPE_THICKNESS: f32 = 4.;
+
+pub struct Slope {
+    pub object: GameObject,
+    // Degrees, clockwise from horizontal.
+    pub angle: f32,
+    pub half_length: f32,
+}
+
+impl Slope {
+
+    pub fn new(x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, half_length: f32, angle: f32) -> Self {
+        let object = GameObject::new(x, y, bodies, colliders, half_length, SLOPE_THICKNESS, 0., 0.);
+        let body = bodies.rigid_body_mut(object.body_handle).unwrap();
+        body.set_position(na::Isometry2::new(na::Vector2::new(x, y), angle.to_radians()));
+        Self {
+            object,
+            angle,
+            half_length,
+        }
+    }
+
+    pub fn debug_draw(&self, bodies: &DefaultBodySet<f32>) {
+        let center = self.object.position(bodies);
+        let direction = na::Vector2::new(self.angle.to_radians().cos(), self.angle.to_radians().sin()) * self.half_length;
+        let (start, end) = (center - direction, center + direction);
+        draw_line(start.x, start.y, end.x, end.y, SLOPE_THICKNESS * 2., RED);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{self, testing::TestWorld};
+
+    // Drops a box onto a slope at `angle` and returns whether it's grounded and which way it
+    // would slide, on the tick it lands.
+    fn land_on_slope(angle: f32) -> (bool, Option<na::Vector2<f32>>) {
+        let mut world = TestWorld::new();
+        Slope::new(0., 100., &mut world.bodies, &mut world.colliders, 60., angle);
+        let object = world.dynamic(0., 80., 5., 5.);
+        object.set_linear_velocity(&mut world.bodies, na::Vector2::new(0., 30.));
+        let handle = object.collider_handle;
+        for _ in 0..60 {
+            world.step();
+            let grounded = physics::is_grounded(&world.geometrical_world, &world.colliders, handle);
+            let downhill = physics::steep_slope_downhill(&world.geometrical_world, &world.colliders, handle);
+            if grounded || downhill.is_some() {
+                return (grounded, downhill);
+            }
+        }
+        (false, None)
+    }
+
+    #[test]
+    fn steep_slope_is_not_ground_and_slides_downhill() {
+        let (grounded, downhill) = land_on_slope(60.);
+        assert!(!grounded);
+        let downhill = downhill.expect("not touching the slope");
+        // Clockwise in screen space, so the slope falls away to the right.
+        assert!(downhill.y > 0. && downhill.x > 0., "sliding toward {:?}", downhill);
+    }
+
+    #[test]
+    fn gentle_slope_is_ground() {
+        assert_eq!(land_on_slope(20.), (true, None));
+    }
+}
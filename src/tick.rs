@@ -0,0 +1,62 @@
+pub const TICK_RATE: f32 = 60.;
+// Upper bound on catch-up ticks in a single frame, so a long stall doesn't make the next frame
+// even longer.
+pub const MAX_TICKS_PER_FRAME: u32 = 5;
+
+pub struct FixedTimestep {
+    pub dt: f32,
+    pub max_ticks: u32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+
+    pub fn new(tick_rate: f32, max_ticks: u32) -> Self {
+        Self {
+            dt: 1. / tick_rate,
+            max_ticks,
+            accumulator: 0.,
+        }
+    }
+
+    // Adds `elapsed` seconds and returns how many logic ticks should run this frame. Time that
+    // doesn't fit under the clamp is dropped.
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        self.accumulator += elapsed;
+        let mut ticks = 0;
+        while self.accumulator >= self.dt && ticks < self.max_ticks {
+            self.accumulator -= self.dt;
+            ticks += 1;
+        }
+        if self.accumulator >= self.dt {
+            self.accumulator %= self.dt;
+        }
+        ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An eighth of a second is exact in binary, so the counts don't depend on rounding.
+    const RATE: f32 = 8.;
+
+    #[test]
+    fn ticks_for_the_accumulated_time() {
+        let mut timestep = FixedTimestep::new(RATE, u32::MAX);
+        assert_eq!(timestep.advance(0.5), 4);
+        assert_eq!(timestep.advance(0.0625), 0);
+        assert_eq!(timestep.advance(0.0625), 1);
+        // A long stall is caught up in full without the clamp.
+        assert_eq!(timestep.advance(10.), 80);
+    }
+
+    #[test]
+    fn stall_is_clamped_and_the_rest_dropped() {
+        let mut timestep = FixedTimestep::new(RATE, MAX_TICKS_PER_FRAME);
+        assert_eq!(timestep.advance(10.), MAX_TICKS_PER_FRAME);
+        assert_eq!(timestep.advance(0.), 0);
+        assert_eq!(timestep.advance(0.125), 1);
+    }
+}
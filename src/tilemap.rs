@@ -0,0 +1,214 @@
+use nalgebra as na;
+use nphysics2d::object::{DefaultBodySet, DefaultColliderSet};
+use crate::GameObject;
+
+// A rectangle of tiles, in tile coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileRect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+// Merges solid cells into as few rectangles as possible: grow each run to the right first, then
+// downwards while the whole run below is still solid. Rows can differ in length, cells past the
+// end of a row are empty.
+pub fn greedy_mesh(grid: &[Vec<bool>]) -> Vec<TileRect> {
+    let mut used: Vec<Vec<bool>> = grid.iter().map(|row| vec![false; row.len()]).collect();
+    let free = |used: &Vec<Vec<bool>>, x: usize, y: usize| grid[y].get(x) == Some(&true) && !used[y][x];
+    let mut rects = Vec::new();
+    for y in 0..grid.len() {
+        for x in 0..grid[y].len() {
+            if !free(&used, x, y) {
+                continue;
+            }
+            let mut w = 1;
+            while free(&used, x + w, y) {
+                w += 1;
+            }
+            let mut h = 1;
+            while y + h < grid.len() && (x..x + w).all(|cx| free(&used, cx, y + h)) {
+                h += 1;
+            }
+            for row in used.iter_mut().skip(y).take(h) {
+                for cell in row.iter_mut().skip(x).take(w) {
+                    *cell = true;
+                }
+            }
+            rects.push(TileRect { x, y, w, h });
+        }
+    }
+    rects
+}
+
+pub struct Tilemap {
+    pub solid: Vec<Vec<bool>>,
+    pub tile_size: f32,
+    // World position of the top left corner of the map.
+    pub x: f32,
+    pub y: f32,
+    pub objects: Vec<GameObject>,
+    // Tiles covered by each of `objects`, in the same order.
+    rects: Vec<TileRect>,
+}
+
+impl Tilemap {
+
+    // `#` marks a solid tile, anything else is empty.
+    pub fn new(rows: &[&str], tile_size: f32, x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>) -> Self {
+        let solid: Vec<Vec<bool>> = rows.iter().map(|row| row.chars().map(|tile| tile == '#').collect()).collect();
+        let mut tilemap = Self {
+            solid,
+            tile_size,
+            x,
+            y,
+            objects: Vec::new(),
+            rects: Vec::new(),
+        };
+        for rect in greedy_mesh(&tilemap.solid) {
+            tilemap.add_collider(rect, bodies, colliders);
+        }
+        tilemap
+    }
+
+    fn add_collider(&mut self, rect: TileRect, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>) {
+        let half_width = rect.w as f32 * self.tile_size / 2.;
+        let half_height = rect.h as f32 * self.tile_size / 2.;
+        let center_x = self.x + rect.x as f32 * self.tile_size + half_width;
+        let center_y = self.y + rect.y as f32 * self.tile_size + half_height;
+        self.objects.push(GameObject::new(center_x, center_y, bodies, colliders, half_width, half_height, 0., 0.));
+        self.rects.push(rect);
+    }
+
+    // Removes every tile whose centre is within `radius` of `center`. Only the colliders covering
+    // a removed tile are rebuilt, by meshing what is left of them again. Returns whether any tile
+    // was removed.
+    pub fn carve(&mut self, center: na::Vector2<f32>, radius: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>) -> bool {
+        let mut carved = Vec::new();
+        for (y, row) in self.solid.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                let tile_center = na::Vector2::new(self.x + (x as f32 + 0.5) * self.tile_size, self.y + (y as f32 + 0.5) * self.tile_size);
+                if *cell && (tile_center - center).norm() <= radius {
+                    *cell = false;
+                    carved.push((x, y));
+                }
+            }
+        }
+        if carved.is_empty() {
+            return false;
+        }
+        let contains = |rect: &TileRect, (x, y): (usize, usize)| x >= rect.x && x < rect.x + rect.w && y >= rect.y && y < rect.y + rect.h;
+        let mut remesh: Vec<Vec<bool>> = self.solid.iter().map(|row| vec![false; row.len()]).collect();
+        let mut i = 0;
+        while i < self.rects.len() {
+            let rect = self.rects[i];
+            if !carved.iter().any(|&tile| contains(&rect, tile)) {
+                i += 1;
+                continue;
+            }
+            let object = self.objects.swap_remove(i);
+            self.rects.swap_remove(i);
+            colliders.remove(object.collider_handle);
+            bodies.remove(object.body_handle);
+            for (remesh_row, solid_row) in remesh.iter_mut().zip(&self.solid).skip(rect.y).take(rect.h) {
+                remesh_row[rect.x..rect.x + rect.w].copy_from_slice(&solid_row[rect.x..rect.x + rect.w]);
+            }
+        }
+        for rect in greedy_mesh(&remesh) {
+            self.add_collider(rect, bodies, colliders);
+        }
+        true
+    }
+
+    pub fn debug_draw(&self, bodies: &DefaultBodySet<f32>) {
+        for object in &self.objects {
+            object.debug_draw(bodies);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::testing::TestWorld;
+
+    fn grid(rows: &[&str]) -> Vec<Vec<bool>> {
+        rows.iter().map(|row| row.chars().map(|tile| tile == '#').collect()).collect()
+    }
+
+    // Every solid cell covered exactly once and nothing else.
+    fn assert_covers(grid: &[Vec<bool>], rects: &[TileRect]) {
+        for (y, row) in grid.iter().enumerate() {
+            for (x, &solid) in row.iter().enumerate() {
+                let covering = rects.iter()
+                    .filter(|rect| x >= rect.x && x < rect.x + rect.w && y >= rect.y && y < rect.y + rect.h)
+                    .count();
+                assert_eq!(covering, solid as usize, "cell {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn merges_a_known_pattern() {
+        let grid = grid(&[
+            "###..",
+            "###..",
+            "....#",
+            "##..#",
+        ]);
+        let rects = greedy_mesh(&grid);
+        assert_eq!(rects, vec![
+            TileRect { x: 0, y: 0, w: 3, h: 2 },
+            TileRect { x: 4, y: 2, w: 1, h: 2 },
+            TileRect { x: 0, y: 3, w: 2, h: 1 },
+        ]);
+        assert_covers(&grid, &rects);
+    }
+
+    #[test]
+    fn empty_grid_has_no_rects() {
+        assert!(greedy_mesh(&[]).is_empty());
+        assert!(greedy_mesh(&grid(&["...", "..."])).is_empty());
+    }
+
+    #[test]
+    fn ragged_rows_use_their_own_length() {
+        let grid = grid(&[
+            "####",
+            "##",
+            "",
+            "###",
+        ]);
+        let rects = greedy_mesh(&grid);
+        assert_eq!(rects.len(), 3);
+        assert_covers(&grid, &rects);
+    }
+
+    #[test]
+    fn carve_removes_tiles_and_their_colliders() {
+        let mut world = TestWorld::new();
+        let mut tilemap = Tilemap::new(&["#####", "#####"], 10., 0., 0., &mut world.bodies, &mut world.colliders);
+        assert_eq!(tilemap.objects.len(), 1);
+        let before = world.colliders.iter().count();
+        // Takes out the middle column.
+        assert!(tilemap.carve(na::Vector2::new(25., 10.), 5.1, &mut world.bodies, &mut world.colliders));
+        assert!(!tilemap.solid[0][2] && !tilemap.solid[1][2]);
+        assert_eq!(tilemap.solid.iter().flatten().filter(|&&solid| solid).count(), 8);
+        // The one collider is split around the hole.
+        assert_eq!(tilemap.objects.len(), 2);
+        assert_eq!(world.colliders.iter().count(), before + 1);
+        assert_covers(&tilemap.solid, &tilemap.rects);
+        let hole = na::Point2::new(25., 10.);
+        world.step();
+        assert!(crate::physics::collider_at_point(&world.geometrical_world, &world.colliders, &hole).is_none());
+    }
+
+    #[test]
+    fn carve_outside_the_map_changes_nothing() {
+        let mut world = TestWorld::new();
+        let mut tilemap = Tilemap::new(&["###"], 10., 0., 0., &mut world.bodies, &mut world.colliders);
+        assert!(!tilemap.carve(na::Vector2::new(100., 100.), 10., &mut world.bodies, &mut world.colliders));
+        assert_eq!(tilemap.objects.len(), 1);
+    }
+}
@@ -0,0 +1,92 @@
+// (seconds since the effect started, time scale) keyframes, linearly interpolated.
+const DEATH_RAMP: [(f32, f32); 3] = [(0., 0.1), (0.6, 0.1), (1.2, 1.)];
+
+pub struct TimeScale {
+    pub scale: f32,
+    pub reduce_motion: bool,
+    ramp_time: Option<f32>,
+}
+
+impl TimeScale {
+
+    pub fn new(reduce_motion: bool) -> Self {
+        Self {
+            scale: 1.,
+            reduce_motion,
+            ramp_time: None,
+        }
+    }
+
+    pub fn slow_motion(&mut self) {
+        if !self.reduce_motion {
+            self.ramp_time = Some(0.);
+        }
+    }
+
+    // Advances the ramp by real (unscaled) time and returns the current scale.
+    pub fn update(&mut self, real_delta: f32) -> f32 {
+        if let Some(time) = self.ramp_time {
+            let time = time + real_delta;
+            self.scale = sample_ramp(&DEATH_RAMP, time);
+            self.ramp_time = if time < DEATH_RAMP[DEATH_RAMP.len() - 1].0 { Some(time) } else { None };
+        }
+        self.scale
+    }
+}
+
+pub fn sample_ramp(ramp: &[(f32, f32)], time: f32) -> f32 {
+    for window in ramp.windows(2) {
+        let (start, from) = window[0];
+        let (end, to) = window[1];
+        if time < end {
+            let t = ((time - start) / (end - start)).max(0.);
+            return from + (to - from) * t;
+        }
+    }
+    ramp[ramp.len() - 1].1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1. / 60.;
+
+    // Scale after `seconds` of real time, one tick at a time.
+    fn run(time_scale: &mut TimeScale, seconds: f32) -> f32 {
+        let mut scale = time_scale.scale;
+        for _ in 0..(seconds / DT).round() as usize {
+            scale = time_scale.update(DT);
+        }
+        scale
+    }
+
+    #[test]
+    fn death_drops_the_time_scale_and_ramps_back() {
+        let mut time_scale = TimeScale::new(false);
+        time_scale.slow_motion();
+        assert_eq!(run(&mut time_scale, 0.1), 0.1);
+        assert_eq!(run(&mut time_scale, 0.4), 0.1);
+        // Halfway through the ramp back up.
+        let scale = run(&mut time_scale, 0.4);
+        assert!((scale - 0.55).abs() < 0.02, "scale {}", scale);
+        assert_eq!(run(&mut time_scale, 0.5), 1.);
+        assert_eq!(run(&mut time_scale, 1.), 1.);
+    }
+
+    #[test]
+    fn reduce_motion_skips_the_slow_motion() {
+        let mut time_scale = TimeScale::new(true);
+        time_scale.slow_motion();
+        assert_eq!(run(&mut time_scale, 0.3), 1.);
+    }
+
+    #[test]
+    fn sample_ramp_interpolates_between_keyframes() {
+        let ramp = [(0., 0.), (1., 1.), (2., 0.5)];
+        assert_eq!(sample_ramp(&ramp, -1.), 0.);
+        assert_eq!(sample_ramp(&ramp, 0.5), 0.5);
+        assert_eq!(sample_ramp(&ramp, 1.5), 0.75);
+        assert_eq!(sample_ramp(&ramp, 3.), 0.5);
+    }
+}
@@ -0,0 +1,32 @@
+use macroquad::*;
+use nphysics2d::object::{DefaultBodySet, DefaultColliderSet};
+use crate::GameObject;
+use crate::physics::set_sensor;
+
+// Upward acceleration while submerged, a bit less than gravity so the hero still sinks slowly.
+pub const WATER_BUOYANCY: f32 = 7.;
+// Fraction of the hero's velocity lost per second in water.
+pub const WATER_DRAG: f32 = 2.;
+// Swimming top speed relative to running or walking on land.
+pub const SWIM_SPEED_FACTOR: f32 = 0.5;
+
+// A pool the hero swims in. Its collider is a sensor, the hero only needs to overlap it.
+pub struct Water {
+    pub object: GameObject,
+}
+
+impl Water {
+
+    pub fn new(x: f32, y: f32, bodies: &mut DefaultBodySet<f32>, colliders: &mut DefaultColliderSet<f32>, width: f32, height: f32) -> Self {
+        let object = GameObject::new(x, y, bodies, colliders, width, height, 0., 0.);
+        set_sensor(colliders, object.collider_handle, true);
+        Self {
+            object,
+        }
+    }
+
+    pub fn draw(&self, bodies: &DefaultBodySet<f32>) {
+        let pos = self.object.position(bodies);
+        draw_rectangle(pos.x - self.object.width, pos.y - self.object.height, self.object.width * 2., self.object.height * 2., Color([40, 110, 220, 120]));
+    }
+}
@@ -0,0 +1,60 @@
+use macroquad::*;
+use nalgebra as na;
+use ncollide2d::bounding_volume::AABB;
+use nphysics2d::object::{Body, BodyStatus, DefaultBodySet, DefaultColliderSet};
+use nphysics2d::world::DefaultGeometricalWorld;
+use crate::particles::Particles;
+use crate::physics::colliders_in_aabb;
+
+// The hero accelerates by about 30 px/s² when running (his move impulse over his body and
+// collider mass), so wind weaker than that can be walked against while anything stronger
+// carries him off.
+pub const MODERATE_WIND: f32 = 15.;
+pub const STRONG_WIND: f32 = 250.;
+
+// A region that accelerates every dynamic body and particle inside it. The acceleration is
+// applied as a velocity change, so light and heavy bodies drift alike.
+pub struct WindZone {
+    pub region: AABB<f32>,
+    pub acceleration: na::Vector2<f32>,
+}
+
+impl WindZone {
+
+    pub fn new(x: f32, y: f32, w: f32, h: f32, acceleration: na::Vector2<f32>) -> Self {
+        Self {
+            region: AABB::new(na::Point2::new(x, y), na::Point2::new(x + w, y + h)),
+            acceleration,
+        }
+    }
+
+    pub fn blow(&self, delta: f32, geometrical_world: &DefaultGeometricalWorld<f32>, bodies: &mut DefaultBodySet<f32>, colliders: &DefaultColliderSet<f32>, particles: &mut Particles) {
+        let mut pushed = Vec::new();
+        for handle in colliders_in_aabb(geometrical_world, colliders, &self.region) {
+            let collider = match colliders.get(handle) {
+                Some(collider) if !collider.is_sensor() => collider,
+                _ => continue,
+            };
+            // A body with several colliders in the zone is only pushed once.
+            if pushed.contains(&collider.body()) {
+                continue;
+            }
+            pushed.push(collider.body());
+            if let Some(body) = bodies.rigid_body_mut(collider.body()) {
+                // Level geometry is made of massless bodies, those stay put.
+                if body.status() != BodyStatus::Dynamic || body.augmented_mass().linear == 0. {
+                    continue;
+                }
+                let velocity = body.velocity().linear + self.acceleration * delta;
+                body.set_linear_velocity(velocity);
+                body.activate();
+            }
+        }
+        particles.push(&self.region, vec2(self.acceleration.x, self.acceleration.y), delta);
+    }
+
+    pub fn draw(&self) {
+        let (mins, maxs) = (self.region.mins(), self.region.maxs());
+        draw_rectangle(mins.x, mins.y, maxs.x - mins.x, maxs.y - mins.y, Color([200, 200, 255, 40]));
+    }
+}
@@ -0,0 +1,1980 @@
+use macroquad::*;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use nalgebra as na;
+use ncollide2d::bounding_volume::AABB;
+use nphysics2d::world::{DefaultMechanicalWorld, DefaultGeometricalWorld};
+use nphysics2d::object::{Body, DefaultBodySet, DefaultColliderSet, DefaultColliderHandle};
+use nphysics2d::joint::DefaultJointConstraintSet;
+use nphysics2d::force_generator::DefaultForceGeneratorSet;
+use nphysics2d::math::{Force, ForceType, Velocity};
+use crate::GameObject;
+use crate::ai::AiState;
+use crate::assets::Sprite;
+use crate::attack::{hero_attack, AttackDef, BLOCK_PUSHBACK, HERO_ATTACK, HERO_DOWN_ATTACK, POUND_CARVE_RADIUS, POUND_DAMAGE, POUND_HEIGHT, POUND_RADIUS, POUND_RECOVERY, POUND_SPEED, POUND_STUN, STOMP_BOUNCE, STOMP_DAMAGE, STOMP_SPEED};
+use crate::audio::AudioManager;
+use crate::breakable::BreakableBlock;
+use crate::camera::{snap_to_pixel, FocusTarget, FollowCamera};
+use crate::coin::Coin;
+use crate::collisions::{self, ColliderKind, CollisionRegistry};
+use crate::combo::{Combo, ComboDef, HERO_COMBO};
+use crate::conveyor::Conveyor;
+use crate::crumble::CrumblePlatform;
+use crate::debug::DebugOverlays;
+use crate::enemy::{BossPhase, Enemy, EnemyCorpse};
+use crate::flash::ScreenFlash;
+use crate::floating_text::FloatingText;
+use crate::formation::{Formation, FormationShape};
+use crate::freeze::Freezer;
+use crate::grab::{Grab, THROWN_ENEMY_DAMAGE};
+use crate::hero::{Hero, CLIMB_ANIMATION, MOVE_IMPULSE, SKID_ACCELERATION, SKID_ANIMATION, SKID_SPEED, DIE_ANIMATION, HERO_HEALTH, HERO_SHOT_COOLDOWN, HERO_SHOT_DAMAGE, HERO_SHOT_PIERCE, HERO_SHOT_SPEED, IDLE_ANIMATION, RUN_ANIMATION, SLIDE_ACCELERATION};
+use crate::impact::ImpactSounds;
+use crate::input::{InputDelay, InputState};
+use crate::ladder::Ladder;
+use crate::particles::Particles;
+use crate::physics;
+use crate::profile::{Ability, Profile, PROFILE_PATH};
+use crate::projectile::{aim_direction, Projectile};
+use crate::rewind::{Rewind, REWIND_CAPACITY};
+use crate::rng::Rng;
+use crate::rope::{Rope, RopeGrab};
+use crate::save::{SaveGame, SAVE_PATH};
+use crate::secret::SecretWall;
+use crate::settings::{UserSettings, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+use crate::shadow::{draw_shadow, shadow_blob, SHADOW_MAX_HEIGHT};
+use crate::slope::Slope;
+use crate::tilemap::Tilemap;
+use crate::water::{Water, SWIM_SPEED_FACTOR, WATER_BUOYANCY, WATER_DRAG};
+use crate::wind::{WindZone, MODERATE_WIND, STRONG_WIND};
+use crate::time_scale::TimeScale;
+
+// Index of this level in the profile's progress, and what beating it unlocks.
+const LEVEL: u32 = 0;
+const LEVEL_REWARD: Ability = Ability::Dash;
+const SPAWN_FACING: f32 = 1.;
+// Seeds the world's random rolls, like enemies blocking.
+const LEVEL_SEED: u64 = 0x5eed;
+// Debug snapshots taken on pause are appended here.
+pub const SNAPSHOT_PATH: &str = "snapshot.log";
+// The floor is part of the level rather than as wide as the window, so the window size can't
+// change the simulation.
+const GROUND_WIDTH: f32 = 800.;
+// Room the camera gets around the level geometry.
+const LEVEL_BOUNDS_MARGIN: f32 = 40.;
+// A swing only hits on the tick it starts, so the debug view keeps its box up a little longer
+// to be visible at all.
+const HITBOX_DEBUG_TICKS: u32 = 6;
+const POUND_DUST: usize = 12;
+const HITBOX_DEBUG_COLOR: Color = Color([255, 0, 255, 90]);
+const DAMAGE_TEXT_COLOR: Color = Color([230, 40, 40, 255]);
+// Screen flash durations in seconds. The hurt flash is translucent so the hit stays readable.
+const DEATH_FLASH: f32 = 0.8;
+const HURT_FLASH: f32 = 0.25;
+const HURT_FLASH_COLOR: Color = Color([255, 0, 0, 110]);
+// Seconds the camera turns away from the hero to show the boss changing phase, and the gate
+// opening once he's beaten.
+const BOSS_PHASE_FOCUS: f32 = 1.;
+const GATE_FOCUS: f32 = 1.5;
+
+const LEVEL_TILES: [&str; 5] = [
+    "..............",
+    "......####....",
+    "..............",
+    "###.......####",
+    "###...........",
+];
+
+pub struct GameWorld {
+    pub mechanical_world: DefaultMechanicalWorld<f32>,
+    pub geometrical_world: DefaultGeometricalWorld<f32>,
+    pub joint_constraints: DefaultJointConstraintSet<f32>,
+    pub force_generators: DefaultForceGeneratorSet<f32>,
+    pub bodies: DefaultBodySet<f32>,
+    pub colliders: DefaultColliderSet<f32>,
+    pub ground: GameObject,
+    pub block: GameObject,
+    pub hero: Hero,
+    pub hero_start: na::Vector2<f32>,
+    // Way the hero faces when he spawns and respawns, -1 left or 1 right.
+    pub spawn_facing: f32,
+    pub conveyor: Conveyor,
+    pub crumble_platforms: Vec<CrumblePlatform>,
+    pub tilemap: Tilemap,
+    // Ground pounds knock tiles out of the tilemap.
+    pub destructible_terrain: bool,
+    pub slope: Slope,
+    pub breakables: Vec<BreakableBlock>,
+    pub enemies: Vec<Enemy>,
+    // Death animations of enemies already gone from the physics world.
+    pub corpses: Vec<EnemyCorpse>,
+    pub projectiles: Vec<Projectile>,
+    pub secret_walls: Vec<SecretWall>,
+    // Blocks the way past the boss until he is beaten.
+    pub boss_gate: GameObject,
+    pub coins: Vec<Coin>,
+    pub ladders: Vec<Ladder>,
+    pub ropes: Vec<Rope>,
+    pub rope_grab: RopeGrab,
+    pub water: Vec<Water>,
+    pub wind_zones: Vec<WindZone>,
+    pub coins_collected: u32,
+    // Progress across levels, kept across restarts and written out when a level is beaten.
+    pub profile: Profile,
+    pub collisions: CollisionRegistry,
+    pub particles: Particles,
+    pub floating_text: FloatingText,
+    pub camera: FollowCamera,
+    pub time_scale: TimeScale,
+    pub flash: ScreenFlash,
+    pub audio: AudioManager,
+    impacts: ImpactSounds,
+    pub rewind: Rewind,
+    pub grab: Grab,
+    pub freezer: Freezer,
+    pub attack: &'static AttackDef,
+    // Ticks left before the hero recovers from his last attack.
+    pub recovery: u32,
+    // Facing latched when the current attack started, kept until it has recovered.
+    pub attack_flip: bool,
+    // Ticks since the current attack started.
+    pub attack_tick: u32,
+    pub combo: Combo,
+    // Last boss phase the camera was turned to show.
+    boss_phase: usize,
+    // Where the last swing hit and how many more ticks the debug view keeps drawing it.
+    pub hitbox: Option<(AABB<f32>, u32)>,
+    pub flip: bool,
+    pub animation: usize,
+    // Logic ticks run so far and the simulated seconds they add up to, kept across restarts.
+    pub tick: u64,
+    pub game_time: f64,
+    input_delay: InputDelay,
+    // Whether the last step was over `MAX_STEP_CONTACTS`, so the warning fires once per spike.
+    too_many_contacts: bool,
+    previous_input: InputState,
+    // Saving, writing the profile and printing warnings. Off for the determinism check's shadow
+    // world, which mustn't repeat what the real world already did.
+    pub side_effects: bool,
+    settings: UserSettings,
+    rng: Rng,
+}
+
+impl GameWorld {
+
+    pub fn new(settings: &UserSettings, profile: Profile) -> Self {
+        let mut mechanical_world = DefaultMechanicalWorld::new(na::Vector2::new(0.0, 9.81));
+        let mut geometrical_world = DefaultGeometricalWorld::new();
+        let mut joint_constraints = DefaultJointConstraintSet::new();
+        let mut bodies = DefaultBodySet::new();
+        let mut colliders = DefaultColliderSet::new();
+
+        let ground = GameObject::new(GROUND_WIDTH/2., 480., &mut bodies, &mut colliders, GROUND_WIDTH, 10., 0., 0.);
+        let block = GameObject::new(100., 400., &mut bodies, &mut colliders, 10., 10., 75., 1.);
+        let mut hero = Hero::new(10., 350., &mut bodies, &mut colliders);
+        hero.impulse_scale = settings.impulse_scale;
+        let conveyor = Conveyor::new(300., 465., &mut bodies, &mut colliders, 60., 5., 40.);
+        let crumble_platforms = vec![
+            CrumblePlatform::new(780., 420., &mut bodies, &mut colliders, 30., 5.),
+        ];
+        let tilemap = Tilemap::new(&LEVEL_TILES, 20., 150., 300., &mut bodies, &mut colliders);
+        let slope = Slope::new(620., 420., &mut bodies, &mut colliders, 40., 60.);
+        let breakables = vec![
+            BreakableBlock::new(200., 455., &mut bodies, &mut colliders, 10., 10., 1),
+            BreakableBlock::new(450., 455., &mut bodies, &mut colliders, 10., 10., 3),
+        ];
+        let mut enemies = vec![
+            Enemy::new(550., 455., &mut bodies, &mut colliders, 2).with_block_chance(0.3),
+            Enemy::boss(750., 445., &mut bodies, &mut colliders, vec![
+                BossPhase { health: 10, behavior: AiState::Patrol { left: 650., right: 850. } },
+                BossPhase { health: 6, behavior: AiState::Charge },
+                BossPhase { health: 3, behavior: AiState::Ranged },
+            ]),
+        ];
+        // Groups to the left of the start, and a line past the boss gate.
+        let formations = [
+            Formation {
+                anchor: na::Vector2::new(-330., 470.),
+                shape: FormationShape::Arc { count: 3, radius: 30., spread: 120. },
+                health: 1,
+            },
+            Formation {
+                anchor: na::Vector2::new(-220., 433.),
+                shape: FormationShape::Grid { columns: 2, rows: 2, spacing: 22. },
+                health: 1,
+            },
+            Formation {
+                anchor: na::Vector2::new(1120., 455.),
+                shape: FormationShape::Line { count: 3, spacing: 25. },
+                health: 1,
+            },
+        ];
+        for formation in &formations {
+            enemies.extend(formation.spawn(&mut bodies, &mut colliders));
+        }
+        let enemies: Vec<Enemy> = enemies.into_iter().map(|enemy| enemy.scaled(settings.difficulty)).collect();
+        let secret_walls = vec![SecretWall::new(210., 360., 60., 40., false)];
+        let coins = vec![
+            Coin::new(60., 460., &mut bodies, &mut colliders),
+            Coin::new(80., 460., &mut bodies, &mut colliders),
+            Coin::new(270., 305., &mut bodies, &mut colliders),
+            Coin::new(290., 305., &mut bodies, &mut colliders),
+        ];
+        let ladders = vec![Ladder::new(360., 395., &mut bodies, &mut colliders, 6., 75.)];
+        let ropes = vec![Rope::new(500., 360., 70., &mut bodies)];
+        let water = vec![Water::new(1000., 440., &mut bodies, &mut colliders, 60., 30.)];
+        let wind_zones = vec![
+            WindZone::new(20., 380., 100., 90., na::Vector2::new(MODERATE_WIND, 0.)),
+            WindZone::new(1080., 380., 100., 90., na::Vector2::new(-STRONG_WIND, 0.)),
+        ];
+        let mut collisions = CollisionRegistry::new();
+        collisions.tag(hero.object.collider_handle, ColliderKind::Hero);
+        physics::ignore_corpses(&mut colliders, hero.object.collider_handle);
+        for enemy in &enemies {
+            collisions.tag(enemy.object.collider_handle, ColliderKind::Enemy);
+            physics::ignore_corpses(&mut colliders, enemy.object.collider_handle);
+        }
+        for coin in &coins {
+            collisions.tag(coin.object.collider_handle, ColliderKind::Coin);
+        }
+        collisions.register(ColliderKind::Hero, ColliderKind::Coin, collect_coin);
+        collisions.register(ColliderKind::Enemy, ColliderKind::Enemy, thrown_enemy_impact);
+        let boss_gate = GameObject::new(900., 430., &mut bodies, &mut colliders, 5., 40., 0., 0.);
+        let hero_start = hero.object.position(&bodies);
+        let mut camera = FollowCamera::new(vec2(hero_start.x, hero_start.y));
+        camera.zoom = settings.zoom;
+        camera.pixel_snap = settings.pixel_snap;
+        // Everything past the boss gate is its own room.
+        camera.rooms = vec![
+            Rect { x: -400., y: 0., w: 1280., h: 500. },
+            Rect { x: 880., y: 0., w: 400., h: 500. },
+        ];
+        mechanical_world.maintain(&mut geometrical_world,
+                                  &mut bodies,
+                                  &mut colliders,
+                                  &mut joint_constraints,);
+        let mut world = Self {
+            mechanical_world,
+            geometrical_world,
+            joint_constraints,
+            force_generators: DefaultForceGeneratorSet::new(),
+            bodies,
+            colliders,
+            ground,
+            block,
+            hero,
+            hero_start,
+            spawn_facing: SPAWN_FACING,
+            conveyor,
+            crumble_platforms,
+            tilemap,
+            destructible_terrain: true,
+            slope,
+            breakables,
+            enemies,
+            corpses: Vec::new(),
+            projectiles: Vec::new(),
+            secret_walls,
+            boss_gate,
+            coins,
+            ladders,
+            ropes,
+            rope_grab: RopeGrab::new(),
+            water,
+            wind_zones,
+            coins_collected: 0,
+            profile,
+            collisions,
+            particles: Particles::new(),
+            floating_text: FloatingText::new(),
+            camera,
+            time_scale: TimeScale::new(settings.reduce_motion),
+            flash: ScreenFlash::new(settings.reduce_motion),
+            audio: AudioManager::new(settings),
+            impacts: ImpactSounds::new(),
+            rewind: Rewind::new(REWIND_CAPACITY),
+            grab: Grab::new(),
+            freezer: Freezer::new(),
+            attack: &HERO_ATTACK,
+            recovery: 0,
+            attack_flip: false,
+            attack_tick: 0,
+            combo: Combo::new(ComboDef::parse(HERO_COMBO)),
+            boss_phase: 0,
+            hitbox: None,
+            flip: SPAWN_FACING < 0.,
+            animation: IDLE_ANIMATION,
+            tick: 0,
+            game_time: 0.,
+            input_delay: InputDelay::new(settings.input_delay as usize),
+            too_many_contacts: false,
+            previous_input: InputState::default(),
+            side_effects: true,
+            settings: settings.clone(),
+            rng: Rng::new(LEVEL_SEED),
+        };
+        world.camera.bounds = Some(world.computed_bounds(LEVEL_BOUNDS_MARGIN));
+        world
+    }
+
+    // Takes changed settings on board mid-game. Difficulty and input delay are baked into the
+    // enemies and the queued input, so they only change from the next restart.
+    pub fn apply_settings(&mut self, settings: &UserSettings) {
+        self.hero.impulse_scale = settings.impulse_scale;
+        self.camera.zoom = settings.zoom;
+        self.camera.pixel_snap = settings.pixel_snap;
+        self.time_scale.reduce_motion = settings.reduce_motion;
+        self.flash.reduce_motion = settings.reduce_motion;
+        self.audio.set_volumes(settings);
+        self.settings = settings.clone();
+    }
+
+    // Everything the level geometry covers plus `margin` on every side. A level without any
+    // geometry gets one virtual screen.
+    pub fn computed_bounds(&self, margin: f32) -> Rect {
+        match physics::static_bounds(&self.colliders, &self.bodies) {
+            Some(bounds) => Rect {
+                x: bounds.mins().x - margin,
+                y: bounds.mins().y - margin,
+                w: bounds.maxs().x - bounds.mins().x + margin * 2.,
+                h: bounds.maxs().y - bounds.mins().y + margin * 2.,
+            },
+            None => Rect { x: 0., y: 0., w: VIRTUAL_WIDTH, h: VIRTUAL_HEIGHT },
+        }
+    }
+
+    pub fn save_game(&self) -> SaveGame {
+        let position = self.hero.object.position(&self.bodies);
+        SaveGame {
+            hero_x: position.x,
+            hero_y: position.y,
+            hero_health: self.hero.health,
+            boss_defeated: !self.enemies.iter().any(|enemy| enemy.is_boss()),
+        }
+    }
+
+    // Restarts the level and then puts back what the save recorded.
+    pub fn load_game(&mut self, save: &SaveGame) {
+        self.restart_level();
+        self.hero.respawn(&mut self.bodies, na::Vector2::new(save.hero_x, save.hero_y));
+        self.hero.health = save.hero_health.clamp(1, HERO_HEALTH);
+        if save.boss_defeated {
+            let (bosses, enemies) = self.enemies.drain(..).partition(|enemy: &Enemy| enemy.is_boss());
+            self.enemies = enemies;
+            for boss in bosses {
+                self.collisions.untag(boss.object.collider_handle);
+                boss.despawn(&mut self.bodies, &mut self.colliders);
+            }
+            self.set_sensor(self.boss_gate.collider_handle, true);
+        }
+    }
+
+    // Makes a collider passable (sensor) or solid again at runtime, for scripted events.
+    pub fn set_sensor(&mut self, handle: DefaultColliderHandle, sensor: bool) {
+        physics::set_passable(&mut self.colliders, handle, sensor);
+    }
+
+    // Throws the whole level away and builds it again from the level data, as if it were just loaded.
+    pub fn restart_level(&mut self) {
+        let settings = self.settings.clone();
+        // Inputs already in flight aren't part of the level, keep them queued unless the delay
+        // itself changed.
+        let input_delay = std::mem::replace(&mut self.input_delay, InputDelay::new(0));
+        let keep_queue = input_delay.delay() == settings.input_delay as usize;
+        let (tick, game_time, side_effects) = (self.tick, self.game_time, self.side_effects);
+        let profile = std::mem::take(&mut self.profile);
+        *self = GameWorld::new(&settings, profile);
+        if keep_queue {
+            self.input_delay = input_delay;
+        }
+        self.side_effects = side_effects;
+        self.tick = tick;
+        self.game_time = game_time;
+    }
+
+    // One logic tick of `delta` seconds.
+    pub fn update(&mut self, delta: f32, input: &InputState) {
+        self.tick += 1;
+        self.game_time += delta as f64;
+        let input = &self.input_delay.push(*input);
+        if input.restart && !self.previous_input.restart {
+            self.restart_level();
+            // Carry the held key over so the fresh level doesn't restart again next tick.
+            self.previous_input = *input;
+            return;
+        }
+        if input.quick_save && !self.previous_input.quick_save && self.side_effects {
+            if let Err(error) = self.save_game().save(SAVE_PATH) {
+                eprintln!("Failed to save {}: {}", SAVE_PATH, error);
+            }
+        }
+        if input.quick_load && !self.previous_input.quick_load {
+            match SaveGame::load(SAVE_PATH) {
+                Ok(save) => {
+                    self.load_game(&save);
+                    self.previous_input = *input;
+                    return;
+                }
+                Err(error) if self.side_effects => eprintln!("Failed to load {}: {}", SAVE_PATH, error),
+                Err(_) => {}
+            }
+        }
+        self.mechanical_world.set_timestep(delta);
+        let fall_speed = self.hero.object.linear_velocity(&self.bodies).y;
+        // Debug builds only: holding rewind replays recorded snapshots backwards instead of
+        // stepping the world.
+        let rewinding = cfg!(debug_assertions) && input.rewind && self.rewind.rewind(&mut self.bodies);
+        if let Some(collider) = self.colliders.get_mut(self.hero.object.collider_handle) {
+            collider.enable_ccd(self.hero.is_knocked_back());
+        }
+        if !rewinding {
+            self.mechanical_world.step(
+                &mut self.geometrical_world,
+                &mut self.bodies,
+                &mut self.colliders,
+                &mut self.joint_constraints,
+                &mut self.force_generators,
+            );
+            if cfg!(debug_assertions) {
+                self.rewind.record(&self.bodies);
+                self.check_contact_count();
+            }
+            collisions::dispatch(self);
+            self.impacts.update(delta, &self.geometrical_world, &self.colliders, &self.bodies, &mut self.audio);
+        }
+        if let Some(downhill) = physics::steep_slope_downhill(&self.geometrical_world, &self.colliders, self.hero.object.collider_handle) {
+            let velocity = self.hero.object.linear_velocity(&self.bodies) + downhill * SLIDE_ACCELERATION * delta;
+            self.hero.object.set_linear_velocity(&mut self.bodies, velocity);
+        }
+        for wind_zone in &self.wind_zones {
+            wind_zone.blow(delta, &self.geometrical_world, &mut self.bodies, &self.colliders, &mut self.particles);
+        }
+        self.hero.swimming = self.water.iter()
+            .any(|water| physics::is_overlapping(&self.geometrical_world, &self.colliders, self.hero.object.collider_handle, water.object.collider_handle));
+        if self.hero.swimming {
+            let mut velocity = self.hero.object.linear_velocity(&self.bodies);
+            velocity.y -= WATER_BUOYANCY * delta;
+            velocity *= (1. - WATER_DRAG * delta).max(0.);
+            self.hero.object.set_linear_velocity(&mut self.bodies, velocity);
+        }
+        self.conveyor.carry(&self.geometrical_world, &mut self.bodies, &self.colliders, &[&self.hero.object, &self.block]);
+        for platform in &mut self.crumble_platforms {
+            platform.update(delta, &self.geometrical_world, &mut self.bodies, &mut self.colliders, &[&self.hero.object, &self.block]);
+        }
+        if fall_speed > STOMP_SPEED {
+            for breakable in &mut self.breakables {
+                if physics::is_standing_on(&self.geometrical_world, &self.colliders, self.hero.object.collider_handle, breakable.object.collider_handle) {
+                    let damage = breakable.hit(STOMP_DAMAGE);
+                    show_damage(&mut self.floating_text, &self.bodies, &breakable.object, damage);
+                }
+            }
+        }
+        let hero_alive = !self.hero.is_dead();
+        let hero_health = self.hero.health;
+        let grounded = physics::is_grounded(&self.geometrical_world, &self.colliders, self.hero.object.collider_handle);
+        self.hero.update(delta, grounded);
+        if self.hero.should_respawn() {
+            self.hero.respawn(&mut self.bodies, self.hero_start);
+            self.conveyor.release(self.hero.object.body_handle);
+            self.flip = self.spawn_facing < 0.;
+        }
+        let hero_pos = self.hero.object.position(&self.bodies);
+        for enemy in &mut self.enemies {
+            if enemy.turns_at_ledges && enemy.is_patrolling() {
+                let handle = enemy.object.collider_handle;
+                let position = enemy.object.position(&self.bodies);
+                if physics::is_grounded(&self.geometrical_world, &self.colliders, handle)
+                    && physics::is_ledge_ahead(&self.geometrical_world, &self.colliders, position, enemy.facing(), handle) {
+                    enemy.turn_around();
+                }
+            }
+            enemy.think(delta, hero_pos, &mut self.bodies, &mut self.projectiles);
+        }
+        let mut struck = Vec::new();
+        for projectile in &mut self.projectiles {
+            projectile.update(delta);
+            let hit = physics::colliders_in_aabb(&self.geometrical_world, &self.colliders, &projectile.aabb());
+            if !projectile.friendly {
+                if hit.contains(&self.hero.object.collider_handle) && projectile.strike(self.hero.object.collider_handle) {
+                    self.hero.hurt(projectile.damage);
+                }
+                continue;
+            }
+            for enemy in &mut self.enemies {
+                if hit.contains(&enemy.object.collider_handle) && projectile.strike(enemy.object.collider_handle) {
+                    let damage = enemy.hit(projectile.damage);
+                    show_damage(&mut self.floating_text, &self.bodies, &enemy.object, damage);
+                    struck.push(enemy.object.collider_handle);
+                }
+            }
+        }
+        self.projectiles.retain(|projectile| !projectile.is_expired());
+        for enemy in &mut self.enemies {
+            match physics::contact_normal(&self.geometrical_world, &self.colliders, self.hero.object.collider_handle, enemy.object.collider_handle) {
+                Some(normal) if normal.y > physics::standing_normal_y() => {
+                    let damage = enemy.hit(STOMP_DAMAGE);
+                    show_damage(&mut self.floating_text, &self.bodies, &enemy.object, damage);
+                    struck.push(enemy.object.collider_handle);
+                    let velocity = self.hero.object.linear_velocity(&self.bodies);
+                    self.hero.object.set_linear_velocity(&mut self.bodies, na::Vector2::new(velocity.x, -STOMP_BOUNCE * self.settings.impulse_scale));
+                }
+                Some(_) if enemy.is_stunned() => {}
+                Some(normal) => {
+                    let hurt = self.hero.hurt(enemy.contact_damage);
+                    // The normal points from the hero to the enemy, so the shove goes the other way.
+                    if hurt {
+                        self.hero.knock_back(&mut self.bodies, -normal.x.signum());
+                    }
+                }
+                None => {}
+            }
+        }
+        self.alert_allies(&struck);
+        self.recovery = self.recovery.saturating_sub(1);
+        if self.recovery > 0 {
+            self.attack_tick += 1;
+            if self.attack.lunge != 0. && self.attack_tick == self.attack.lunge_tick {
+                let direction = if self.attack_flip { -1. } else { 1. };
+                let mut velocity = self.hero.object.linear_velocity(&self.bodies);
+                velocity.x += direction * self.attack.lunge * self.settings.impulse_scale;
+                self.hero.object.set_linear_velocity(&mut self.bodies, velocity);
+            }
+        }
+        self.hitbox = match self.hitbox.take() {
+            Some((hitbox, ticks)) if ticks > 1 => Some((hitbox, ticks - 1)),
+            _ => None,
+        };
+        // With auto attack a held key counts as a fresh press every tick, so the next swing comes
+        // out on the first tick recovery or a combo's cancel window allows it.
+        let attack_pressed = input.attack && (!self.previous_input.attack || self.settings.auto_attack);
+        if attack_pressed && !self.hero.is_dead() && !self.hero.pounding && !self.rope_grab.is_attached() {
+            if input.down && !grounded {
+                if self.recovery == 0 {
+                    self.combo.reset();
+                    self.attack = &HERO_DOWN_ATTACK;
+                    self.hero.pounding = true;
+                }
+            } else if !input.up && !input.down && grounded {
+                if let Some(attack) = self.combo.next(self.recovery) {
+                    self.start_attack(attack);
+                }
+            } else if self.recovery == 0 {
+                self.combo.reset();
+                self.start_attack(hero_attack(input.up, input.down, grounded));
+            }
+        }
+        self.update_ground_pound(grounded);
+        if input.shoot && !self.hero.is_dead() && self.hero.shot_cooldown <= 0. {
+            let facing = na::Vector2::new(if self.flip { -1. } else { 1. }, 0.);
+            let position = self.hero.object.position(&self.bodies);
+            let velocity = self.aim(input).unwrap_or(facing) * HERO_SHOT_SPEED;
+            self.projectiles.push(Projectile::new(position, velocity, HERO_SHOT_DAMAGE).friendly(HERO_SHOT_PIERCE));
+            self.hero.shot_cooldown = HERO_SHOT_COOLDOWN;
+        }
+        if input.grab && !self.previous_input.grab && !self.hero.is_dead() {
+            if self.grab.is_carrying() {
+                let aim = self.aim(input);
+                if let Some(enemy) = self.carried_enemy() {
+                    enemy.carried = false;
+                    enemy.thrown = true;
+                }
+                self.grab.throw(&mut self.bodies, if self.flip { -1. } else { 1. }, aim);
+            } else if !self.grab.try_grab(&mut self.bodies, &self.hero.object, &self.block) {
+                // Only stunned enemies hold still long enough to be picked up.
+                for enemy in &mut self.enemies {
+                    if enemy.is_stunned() && self.grab.try_grab(&mut self.bodies, &self.hero.object, &enemy.object) {
+                        enemy.carried = true;
+                        break;
+                    }
+                }
+            }
+        } else if self.hero.is_dead() {
+            if let Some(enemy) = self.carried_enemy() {
+                enemy.carried = false;
+            }
+            self.grab.release(&mut self.bodies);
+        }
+        self.grab.carry(&mut self.bodies, &self.hero.object);
+        if self.profile.has_ability(Ability::CoinMagnet) && !self.hero.is_dead() {
+            let hero_pos = self.hero.object.position(&self.bodies);
+            for coin in &mut self.coins {
+                coin.attract(&mut self.bodies, hero_pos, delta);
+            }
+        }
+        if cfg!(debug_assertions) && input.teleport && !self.previous_input.teleport {
+            let point = self.camera.screen_to_world(input.pointer);
+            self.teleport_hero(na::Vector2::new(point.x(), point.y()));
+        }
+        if cfg!(debug_assertions) && input.pick && !self.previous_input.pick {
+            let point = self.camera.screen_to_world(input.pointer);
+            if let Some(handle) = physics::collider_at_point(&self.geometrical_world, &self.colliders, &na::Point2::new(point.x(), point.y())) {
+                if let Some(collider) = self.colliders.get(handle) {
+                    self.freezer.toggle(&mut self.bodies, collider.body());
+                }
+            }
+        }
+        let (broken, intact) = self.breakables.drain(..).partition(|breakable: &BreakableBlock| breakable.is_broken());
+        self.breakables = intact;
+        for breakable in broken {
+            breakable.shatter(&mut self.bodies, &mut self.colliders, &mut self.particles);
+        }
+        let (dead, alive) = self.enemies.drain(..).partition(|enemy: &Enemy| enemy.is_dead());
+        self.enemies = alive;
+        let boss_died = dead.iter().any(|enemy: &Enemy| enemy.is_boss());
+        if (hero_alive && self.hero.is_dead()) || boss_died {
+            self.time_scale.slow_motion();
+        }
+        if hero_alive && self.hero.is_dead() {
+            self.screen_flash(WHITE, DEATH_FLASH);
+        } else if self.hero.health < hero_health {
+            self.screen_flash(HURT_FLASH_COLOR, HURT_FLASH);
+        }
+        if let Some(boss) = self.enemies.iter().find(|enemy| enemy.is_boss() && enemy.phase > self.boss_phase) {
+            self.boss_phase = boss.phase;
+            self.camera.set_focus(FocusTarget::Body(boss.object.body_handle), Some(BOSS_PHASE_FOCUS));
+        }
+        if boss_died {
+            self.set_sensor(self.boss_gate.collider_handle, true);
+            let gate = self.boss_gate.position(&self.bodies);
+            self.camera.set_focus(FocusTarget::Point(vec2(gate.x, gate.y)), Some(GATE_FOCUS));
+            self.complete_level();
+        }
+        for enemy in dead {
+            self.collisions.untag(enemy.object.collider_handle);
+            self.corpses.push(enemy.die(&mut self.bodies, &mut self.colliders, self.settings.enemy_ragdolls));
+        }
+        for corpse in &mut self.corpses {
+            corpse.update(delta);
+        }
+        let (finished, fading) = self.corpses.drain(..).partition(|corpse: &EnemyCorpse| corpse.is_finished());
+        self.corpses = fading;
+        for corpse in finished {
+            corpse.despawn(&mut self.bodies, &mut self.colliders);
+        }
+        self.particles.update(delta);
+        self.floating_text.update(delta);
+        let hero_pos = self.hero.object.position(&self.bodies);
+        for secret_wall in &mut self.secret_walls {
+            secret_wall.update(delta, hero_pos);
+        }
+        let near_ledge = physics::is_near_ledge(&self.geometrical_world, &self.colliders, hero_pos, self.hero.object.collider_handle);
+        let peek = if self.animation == IDLE_ANIMATION && near_ledge {
+            if input.down { 1. } else if input.up { -1. } else { 0. }
+        } else {
+            0.
+        };
+        self.camera.follow(delta, &self.bodies, vec2(hero_pos.x, hero_pos.y), grounded, peek);
+        self.apply_input(input);
+        self.previous_input = *input;
+    }
+
+    fn check_contact_count(&mut self) {
+        let count = physics::contact_count(&self.geometrical_world, &self.colliders);
+        let too_many = count > physics::MAX_STEP_CONTACTS;
+        if too_many && !self.too_many_contacts && self.side_effects {
+            eprintln!("Warning: {} active contacts in one step, more than {}", count, physics::MAX_STEP_CONTACTS);
+        }
+        self.too_many_contacts = too_many;
+    }
+
+    fn start_attack(&mut self, attack: &'static AttackDef) {
+        let pos = self.hero.object.position(&self.bodies);
+        self.attack = attack;
+        self.attack_flip = self.flip;
+        self.attack_tick = 0;
+        let mut velocity = self.hero.object.linear_velocity(&self.bodies);
+        velocity.x = self.attack.momentum.apply(velocity.x);
+        let hitbox = self.attack.hitbox(pos, self.attack_flip);
+        self.hitbox = Some((hitbox.clone(), HITBOX_DEBUG_TICKS));
+        let hit = physics::colliders_in_aabb(&self.geometrical_world, &self.colliders, &hitbox);
+        let mut connected = false;
+        for breakable in &mut self.breakables {
+            if hit.contains(&breakable.object.collider_handle) {
+                let damage = breakable.hit(self.attack.damage);
+                show_damage(&mut self.floating_text, &self.bodies, &breakable.object, damage);
+                connected = true;
+            }
+        }
+        let direction = if self.attack_flip { -1. } else { 1. };
+        let mut struck = Vec::new();
+        let mut blocked = false;
+        for enemy in &mut self.enemies {
+            if hit.contains(&enemy.object.collider_handle) {
+                if enemy.block_chance > 0. && self.rng.chance(enemy.block_chance) {
+                    enemy.block();
+                    blocked = true;
+                    continue;
+                }
+                let damage = enemy.hit(self.attack.damage);
+                show_damage(&mut self.floating_text, &self.bodies, &enemy.object, damage);
+                if damage > 0 {
+                    enemy.knock_back(&mut self.bodies, direction, self.attack.knockback * self.settings.impulse_scale);
+                }
+                struck.push(enemy.object.collider_handle);
+                connected = true;
+            }
+        }
+        self.alert_allies(&struck);
+        self.recovery = self.combo.recovery(self.attack, connected);
+        if connected && self.attack.bounce_on_hit {
+            velocity.y = -STOMP_BOUNCE * self.settings.impulse_scale;
+        }
+        if blocked {
+            velocity.x = -direction * BLOCK_PUSHBACK * self.settings.impulse_scale;
+        }
+        self.hero.object.set_linear_velocity(&mut self.bodies, velocity);
+    }
+
+    // Everything needed to inspect this exact tick later: counters, the random generator, the hero,
+    // every enemy and every rigid body. `key = value` lines, grouped under `[section]` headers.
+    pub fn debug_dump(&self) -> String {
+        let mut dump = String::new();
+        let vector = |v: na::Vector2<f32>| format!("{}, {}", v.x, v.y);
+        dump += &format!("tick = {}\ngame_time = {}\nseed = {}\nrng_state = {}\ncoins_collected = {}\n",
+                         self.tick, self.game_time, LEVEL_SEED, self.rng.state(), self.coins_collected);
+        dump += "\n[hero]\n";
+        dump += &format!("position = {}\nvelocity = {}\nhealth = {}\nfacing = {}\nanimation = {}\n",
+                         vector(self.hero.object.position(&self.bodies)), vector(self.hero.object.linear_velocity(&self.bodies)),
+                         self.hero.health, if self.flip { "left" } else { "right" }, self.animation);
+        dump += &format!("air_time = {}\nclimbing = {}\nswimming = {}\npounding = {}\ndashing = {}\nknocked_back = {}\nrecovery = {}\n",
+                         self.hero.air_time, self.hero.climbing, self.hero.swimming, self.hero.pounding,
+                         self.hero.is_dashing(), self.hero.is_knocked_back(), self.recovery);
+        for (i, enemy) in self.enemies.iter().enumerate() {
+            dump += &format!("\n[enemy {}]\n", i);
+            dump += &format!("position = {}\nvelocity = {}\nhealth = {}\nai = {:?}\nfacing = {}\nstunned = {}\ncarried = {}\nthrown = {}\n",
+                             vector(enemy.object.position(&self.bodies)), vector(enemy.object.linear_velocity(&self.bodies)),
+                             enemy.health, enemy.ai.current(), enemy.facing(), enemy.is_stunned(), enemy.carried, enemy.thrown);
+        }
+        dump += "\n[bodies]\n";
+        for (handle, body) in self.bodies.iter() {
+            if let Some(rigid_body) = self.bodies.rigid_body(handle) {
+                let position = rigid_body.position();
+                dump += &format!("{:?} = position {}, angle {}, velocity {}, angular {}, active {}\n",
+                                 handle, vector(position.translation.vector), position.rotation.angle(),
+                                 vector(rigid_body.velocity().linear), rigid_body.velocity().angular, body.is_active());
+            }
+        }
+        dump
+    }
+
+    // Appends a `debug_dump` to `path`, followed by a blank line.
+    pub fn write_debug_dump(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", self.debug_dump())
+    }
+
+    // Beating the boss finishes the level, banking the coins picked up on the way.
+    fn complete_level(&mut self) {
+        self.profile.complete_level(LEVEL);
+        self.profile.unlock_ability(LEVEL_REWARD);
+        self.profile.total_coins += self.coins_collected;
+        if !self.side_effects {
+            return;
+        }
+        if let Err(error) = self.profile.save(PROFILE_PATH) {
+            eprintln!("Failed to save {}: {}", PROFILE_PATH, error);
+        }
+    }
+
+    // Covers the whole screen in `color`, fading out over `duration` seconds. Skipped with
+    // reduce motion on.
+    pub fn screen_flash(&mut self, color: Color, duration: f32) {
+        self.flash.start(color, duration);
+    }
+
+    // Every enemy in `struck` alerts the others within its aggro radius.
+    fn alert_allies(&mut self, struck: &[DefaultColliderHandle]) {
+        for &handle in struck {
+            let (position, radius) = match self.enemies.iter().find(|enemy| enemy.object.collider_handle == handle) {
+                Some(enemy) if enemy.aggro_radius > 0. => (enemy.object.position(&self.bodies), enemy.aggro_radius),
+                _ => continue,
+            };
+            let half_extents = na::Vector2::new(radius, radius);
+            let region = AABB::new(na::Point2::from(position - half_extents), na::Point2::from(position + half_extents));
+            let nearby = physics::colliders_in_aabb(&self.geometrical_world, &self.colliders, &region);
+            for enemy in &mut self.enemies {
+                let other = enemy.object.collider_handle;
+                if other != handle && nearby.contains(&other) {
+                    enemy.alert();
+                }
+            }
+        }
+    }
+
+    // Knocks tiles out of the tilemap and wakes everything up, so whatever stood on them falls.
+    fn carve(&mut self, center: na::Vector2<f32>, radius: f32) {
+        if !self.tilemap.carve(center, radius, &mut self.bodies, &mut self.colliders) {
+            return;
+        }
+        self.particles.burst(center.x, center.y, POUND_DUST, DARKGRAY);
+        for (_, body) in self.bodies.iter_mut() {
+            body.activate();
+        }
+    }
+
+    // Debug: moves the hero to `position` at a standstill, nudged out of any wall it lands in.
+    fn teleport_hero(&mut self, position: na::Vector2<f32>) {
+        self.rope_grab.detach(&mut self.bodies, &mut self.joint_constraints);
+        let half_extents = na::Vector2::new(self.hero.object.width, self.hero.object.height);
+        let aabb = AABB::new(na::Point2::from(position - half_extents), na::Point2::from(position + half_extents));
+        let position = position + physics::depenetration(&self.geometrical_world, &self.colliders, &aabb, self.hero.object.collider_handle);
+        if let Some(body) = self.bodies.rigid_body_mut(self.hero.object.body_handle) {
+            body.set_position(na::Isometry2::translation(position.x, position.y));
+            body.set_velocity(Velocity::zero());
+            body.activate();
+        }
+        self.conveyor.release(self.hero.object.body_handle);
+    }
+
+    // The enemy the hero is holding, if it isn't the block.
+    fn carried_enemy(&mut self) -> Option<&mut Enemy> {
+        let carried = self.grab.carried()?;
+        self.enemies.iter_mut().find(|enemy| enemy.object.body_handle == carried)
+    }
+
+    // Direction from the hero to the mouse cursor when aiming with the mouse is on.
+    fn aim(&self, input: &InputState) -> Option<na::Vector2<f32>> {
+        if !self.settings.mouse_aim {
+            return None;
+        }
+        let cursor = self.camera.screen_to_world(input.pointer);
+        aim_direction(self.hero.object.position(&self.bodies), na::Vector2::new(cursor.x(), cursor.y()))
+    }
+
+    // Holds the hero in a straight, fast descent, then damages everything around him on landing.
+    fn update_ground_pound(&mut self, grounded: bool) {
+        if !self.hero.pounding {
+            return;
+        }
+        if self.hero.is_dead() {
+            self.hero.pounding = false;
+            return;
+        }
+        if !grounded {
+            let velocity = self.hero.object.linear_velocity(&self.bodies);
+            self.hero.object.set_linear_velocity(&mut self.bodies, na::Vector2::new(0., velocity.y.max(POUND_SPEED)));
+            return;
+        }
+        self.hero.pounding = false;
+        self.recovery = POUND_RECOVERY;
+        let pos = self.hero.object.position(&self.bodies);
+        let feet = na::Point2::new(pos.x, pos.y + self.hero.object.height);
+        let half_extents = na::Vector2::new(POUND_RADIUS, POUND_HEIGHT);
+        let shockwave = AABB::new(feet - half_extents, feet + half_extents);
+        self.particles.burst(feet.x, feet.y, POUND_DUST, GRAY);
+        if self.destructible_terrain {
+            self.carve(na::Vector2::new(feet.x, feet.y), POUND_CARVE_RADIUS);
+        }
+        let hit = physics::colliders_in_aabb(&self.geometrical_world, &self.colliders, &shockwave);
+        for breakable in &mut self.breakables {
+            if hit.contains(&breakable.object.collider_handle) {
+                let damage = breakable.hit(POUND_DAMAGE);
+                show_damage(&mut self.floating_text, &self.bodies, &breakable.object, damage);
+            }
+        }
+        let mut struck = Vec::new();
+        for enemy in &mut self.enemies {
+            if hit.contains(&enemy.object.collider_handle) {
+                let damage = enemy.hit(POUND_DAMAGE);
+                show_damage(&mut self.floating_text, &self.bodies, &enemy.object, damage);
+                enemy.stun(POUND_STUN);
+                struck.push(enemy.object.collider_handle);
+            }
+        }
+        self.alert_allies(&struck);
+        self.hitbox = Some((shockwave, HITBOX_DEBUG_TICKS));
+    }
+
+    fn apply_input(&mut self, input: &InputState) {
+        if self.hero.is_dead() {
+            if self.hero.climbing {
+                self.hero.set_climbing(&mut self.bodies, false);
+            }
+            self.rope_grab.detach(&mut self.bodies, &mut self.joint_constraints);
+            self.animation = DIE_ANIMATION;
+            return;
+        }
+        if self.update_rope(input) {
+            return;
+        }
+        if self.hero.pounding {
+            self.animation = HERO_DOWN_ATTACK.animation;
+            return;
+        }
+        if self.recovery > 0 {
+            self.animation = self.attack.animation;
+            self.flip = self.attack_flip;
+            return;
+        }
+        if self.update_climbing(input) {
+            return;
+        }
+        if input.toggle_walk && !self.previous_input.toggle_walk {
+            self.hero.walk_toggled = !self.hero.walk_toggled;
+        }
+        if self.hero.is_dashing() {
+            self.hero.hold_dash(&mut self.bodies);
+            self.conveyor.release(self.hero.object.body_handle);
+            self.animation = RUN_ANIMATION;
+            return;
+        }
+        if input.dash && !self.previous_input.dash && self.profile.has_ability(Ability::Dash) {
+            let direction = if self.flip { -1. } else { 1. };
+            if self.hero.dash(&mut self.bodies, direction) {
+                self.conveyor.release(self.hero.object.body_handle);
+                self.animation = RUN_ANIMATION;
+                return;
+            }
+        }
+        self.hero.walking = self.hero.walk_toggled != input.walk;
+        let mut max_speed = if self.hero.walking { self.hero.walk_speed } else { self.hero.run_speed };
+        if self.hero.swimming {
+            max_speed *= SWIM_SPEED_FACTOR;
+        }
+        self.update_skid(if input.right { 1. } else if input.left { -1. } else { 0. });
+        if input.right {
+            self.move_hero(1., max_speed);
+            self.animation = RUN_ANIMATION;
+            self.flip = false;
+        } else if input.left {
+            self.move_hero(-1., max_speed);
+            self.flip = true;
+            self.animation = RUN_ANIMATION;
+        } else {
+            self.animation = IDLE_ANIMATION;
+        }
+        if self.hero.skidding {
+            self.animation = SKID_ANIMATION;
+        }
+        // In water every press is a stroke, which is also how the hero gets out at the surface.
+        // Otherwise, in the air, a wall jump comes before spending the double jump.
+        if input.jump && !self.previous_input.jump {
+            let wall = physics::wall_side(&self.geometrical_world, &self.colliders, self.hero.object.collider_handle);
+            if self.hero.can_jump() || self.hero.swimming {
+                self.hero.jump(&mut self.bodies);
+            } else if let Some(wall) = wall.filter(|_| self.profile.has_ability(Ability::WallJump)) {
+                self.hero.wall_jump(&mut self.bodies, wall);
+            } else if self.hero.air_jump && self.profile.has_ability(Ability::DoubleJump) {
+                self.hero.air_jump = false;
+                self.hero.jump(&mut self.bodies);
+            }
+        }
+        if input.attack {
+            self.animation = self.attack.animation;
+        }
+    }
+
+    // Catches a rope on Up in the air and lets go of it on jump. Returns whether the hero is
+    // hanging from one, in which case the usual movement doesn't apply.
+    fn update_rope(&mut self, input: &InputState) -> bool {
+        if self.rope_grab.is_attached() {
+            if input.jump && !self.previous_input.jump {
+                self.rope_grab.detach(&mut self.bodies, &mut self.joint_constraints);
+            }
+            self.animation = CLIMB_ANIMATION;
+            return true;
+        }
+        if !input.up || self.previous_input.up || self.hero.climbing {
+            return false;
+        }
+        let grounded = physics::is_grounded(&self.geometrical_world, &self.colliders, self.hero.object.collider_handle);
+        let position = self.hero.object.position(&self.bodies);
+        let rope = match self.ropes.iter().position(|rope| rope.can_reach(position)) {
+            Some(rope) if !grounded => rope,
+            _ => return false,
+        };
+        self.rope_grab.attach(&mut self.bodies, &mut self.joint_constraints, &self.ropes, rope, &self.hero.object);
+        self.animation = CLIMB_ANIMATION;
+        true
+    }
+
+    // Mounts, moves along and dismounts ladders. Returns whether the hero is climbing, in which
+    // case the usual movement doesn't apply.
+    fn update_climbing(&mut self, input: &InputState) -> bool {
+        let on_ladder = self.ladders.iter()
+            .any(|ladder| physics::is_overlapping(&self.geometrical_world, &self.colliders, self.hero.object.collider_handle, ladder.object.collider_handle));
+        let grounded = physics::is_grounded(&self.geometrical_world, &self.colliders, self.hero.object.collider_handle);
+        if !self.hero.climbing {
+            // Up grabs the ladder anywhere, Down only when there is ladder below the hero's feet.
+            if on_ladder && (input.up || (input.down && !grounded)) {
+                self.hero.set_climbing(&mut self.bodies, true);
+            } else {
+                return false;
+            }
+        }
+        if !on_ladder || (input.down && grounded) {
+            // Climbed off the top or bottom.
+            self.hero.set_climbing(&mut self.bodies, false);
+            return false;
+        }
+        if input.jump && !self.previous_input.jump {
+            self.hero.set_climbing(&mut self.bodies, false);
+            self.hero.jump(&mut self.bodies);
+            return false;
+        }
+        let direction = if input.up { -1. } else if input.down { 1. } else { 0. };
+        self.hero.climb(&mut self.bodies, direction);
+        self.conveyor.release(self.hero.object.body_handle);
+        self.animation = CLIMB_ANIMATION;
+        true
+    }
+
+    // Starts skidding when the hero is steered against fast ground movement, and stops once he
+    // has turned around or the direction is let go.
+    fn update_skid(&mut self, direction: f32) {
+        let velocity_x = self.hero.object.linear_velocity(&self.bodies).x;
+        if self.hero.skidding {
+            self.hero.skidding = direction != 0. && velocity_x * direction < 0.;
+            return;
+        }
+        let grounded = physics::is_grounded(&self.geometrical_world, &self.colliders, self.hero.object.collider_handle);
+        self.hero.skidding = grounded && velocity_x * direction < -SKID_SPEED;
+    }
+
+    // Pushes the hero along `direction` without taking him past `max_speed`. Anything already
+    // moving him faster that way, like a conveyor or knockback, is left alone.
+    fn move_hero(&mut self, direction: f32, max_speed: f32) {
+        if self.hero.object.linear_velocity(&self.bodies).x * direction >= max_speed {
+            return;
+        }
+        let impulse = if self.hero.skidding { MOVE_IMPULSE * SKID_ACCELERATION } else { MOVE_IMPULSE };
+        let force = Force::linear(na::Vector2::new(impulse * direction, 0.));
+        self.hero.object.rigid_body_mut(&mut self.bodies).apply_force(0, &force, ForceType::Impulse, true);
+        let mut velocity = self.hero.object.linear_velocity(&self.bodies);
+        if velocity.x * direction > max_speed {
+            velocity.x = max_speed * direction;
+            self.hero.object.set_linear_velocity(&mut self.bodies, velocity);
+        }
+    }
+
+    pub fn draw(&self, hero_sprite: Sprite, overlays: &DebugOverlays) {
+        self.ground.debug_draw(&self.bodies);
+        self.block.debug_draw(&self.bodies);
+        if !self.colliders.get(self.boss_gate.collider_handle).is_some_and(|collider| collider.is_sensor()) {
+            self.boss_gate.debug_draw(&self.bodies);
+        }
+        self.conveyor.debug_draw(&self.bodies);
+        for platform in &self.crumble_platforms {
+            platform.draw(&self.bodies);
+        }
+        self.tilemap.debug_draw(&self.bodies);
+        self.slope.debug_draw(&self.bodies);
+        for breakable in &self.breakables {
+            breakable.debug_draw(&self.bodies);
+        }
+        for enemy in &self.enemies {
+            enemy.debug_draw(&self.bodies);
+        }
+        for corpse in &self.corpses {
+            corpse.draw(&self.bodies);
+        }
+        for projectile in &self.projectiles {
+            projectile.draw();
+        }
+        for coin in &self.coins {
+            coin.draw(&self.bodies);
+        }
+        for ladder in &self.ladders {
+            ladder.draw(&self.bodies);
+        }
+        for (i, rope) in self.ropes.iter().enumerate() {
+            let hero = if self.rope_grab.rope() == Some(i) { Some(self.hero.object.position(&self.bodies)) } else { None };
+            rope.draw(hero);
+        }
+        for water in &self.water {
+            water.draw(&self.bodies);
+        }
+        for wind_zone in &self.wind_zones {
+            wind_zone.draw();
+        }
+        self.particles.draw();
+        self.floating_text.draw();
+        self.hero.object.debug_draw(&self.bodies);
+        if overlays.hitboxes {
+            if let Some((hitbox, _)) = &self.hitbox {
+                let (mins, maxs) = (hitbox.mins(), hitbox.maxs());
+                draw_rectangle(mins.x, mins.y, maxs.x - mins.x, maxs.y - mins.y, HITBOX_DEBUG_COLOR);
+            }
+        }
+        if overlays.colliders {
+            for (_, collider) in self.colliders.iter() {
+                let aabb = collider.shape().aabb(collider.position());
+                let (mins, maxs) = (aabb.mins(), aabb.maxs());
+                let color = if collider.is_sensor() { SKYBLUE } else { GREEN };
+                draw_rectangle_lines(mins.x, mins.y, maxs.x - mins.x, maxs.y - mins.y, 1., color);
+            }
+        }
+        let pos = self.hero.object.position(&self.bodies);
+        let mut corner = vec2(pos.x-hero_sprite.width()/2., pos.y-hero_sprite.height()/2.);
+        if self.camera.pixel_snap {
+            corner = snap_to_pixel(corner);
+        }
+        if self.settings.hero_shadow && self.hero.is_visible() {
+            let feet = na::Point2::new(pos.x, pos.y + self.hero.object.height);
+            if let Some(height) = physics::ground_distance(&self.geometrical_world, &self.colliders, feet, SHADOW_MAX_HEIGHT, self.hero.object.collider_handle) {
+                draw_shadow(feet.x, feet.y + height, &shadow_blob(height));
+            }
+        }
+        if self.hero.is_visible() {
+            draw_texture_ex(
+                hero_sprite.texture,
+                corner.x(),
+                corner.y(),
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(vec2(hero_sprite.width(), hero_sprite.height())),
+                    source: if self.flip { Some(Rect {
+                        x: hero_sprite.texture.width(),
+                        y: 0.,
+                        w: -hero_sprite.texture.width(),
+                        h: hero_sprite.texture.height(),
+                    }) } else { None },
+                    rotation: 0.,
+                },
+            );
+        }
+        for secret_wall in &self.secret_walls {
+            secret_wall.draw();
+        }
+    }
+}
+
+// Pops a damage number above `target`; hits that did nothing, like on an invulnerable boss, show none.
+fn show_damage(floating_text: &mut FloatingText, bodies: &DefaultBodySet<f32>, target: &GameObject, damage: u32) {
+    if damage == 0 {
+        return;
+    }
+    let position = target.position(bodies);
+    floating_text.spawn(vec2(position.x, position.y - target.height * 2.), damage.to_string(), DAMAGE_TEXT_COLOR);
+}
+
+fn collect_coin(world: &mut GameWorld, _hero: DefaultColliderHandle, coin: DefaultColliderHandle) {
+    if let Some(index) = world.coins.iter().position(|c| c.object.collider_handle == coin) {
+        world.collisions.untag(coin);
+        world.coins.remove(index).collect(&mut world.bodies, &mut world.colliders, &mut world.particles);
+        world.coins_collected += 1;
+    }
+}
+
+// A thrown enemy hurts the first enemy it runs into, then drops like any other body.
+fn thrown_enemy_impact(world: &mut GameWorld, a: DefaultColliderHandle, b: DefaultColliderHandle) {
+    let thrown = |handle| world.enemies.iter().any(|enemy| enemy.object.collider_handle == handle && enemy.thrown);
+    let (thrown, target) = match (thrown(a), thrown(b)) {
+        (true, _) => (a, b),
+        (_, true) => (b, a),
+        _ => return,
+    };
+    for enemy in &mut world.enemies {
+        if enemy.object.collider_handle == thrown {
+            enemy.thrown = false;
+        } else if enemy.object.collider_handle == target {
+            let damage = enemy.hit(THROWN_ENEMY_DAMAGE);
+            show_damage(&mut world.floating_text, &world.bodies, &enemy.object, damage);
+        }
+    }
+    world.alert_allies(&[target]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nphysics2d::object::DefaultBodyHandle;
+    use crate::attack::{MomentumBehavior, HERO_AIR_ATTACK};
+    use crate::ai::AiStack;
+    use crate::enemy::RAGDOLL_FADE_TIME;
+    use crate::hero::DASH_SPEED;
+
+    const DT: f32 = 1. / 60.;
+
+    fn new_world() -> GameWorld {
+        GameWorld::new(&UserSettings::default(), Profile::default())
+    }
+
+    // Moves a body to (x, y) with `velocity`, as if it had got there on its own.
+    fn place(world: &mut GameWorld, body: DefaultBodyHandle, x: f32, y: f32, velocity: na::Vector2<f32>) {
+        let body = world.bodies.rigid_body_mut(body).unwrap();
+        body.set_position(na::Isometry2::translation(x, y));
+        body.set_linear_velocity(velocity);
+        body.activate();
+    }
+
+    // Stands the hero on the ground at `x`. Open ground at 150, clear of the coins, wind and
+    // blocks near the start.
+    fn stand_hero(world: &mut GameWorld, x: f32) -> na::Vector2<f32> {
+        let hero = world.hero.object.body_handle;
+        place(world, hero, x, 460., na::Vector2::zeros());
+        for _ in 0..10 {
+            world.update(DT, &InputState::default());
+        }
+        assert!(physics::is_grounded(&world.geometrical_world, &world.colliders, world.hero.object.collider_handle));
+        world.hero.object.position(&world.bodies)
+    }
+
+    // The hitbox of the swing started by `input`, on the tick it comes out.
+    fn swing(world: &mut GameWorld, input: InputState) -> AABB<f32> {
+        world.update(DT, &InputState { attack: true, ..input });
+        world.hitbox.clone().expect("no swing came out").0
+    }
+
+    #[test]
+    fn up_and_down_attacks_hit_above_and_below_the_hero() {
+        let mut world = new_world();
+        let position = stand_hero(&mut world, 150.);
+        let up = swing(&mut world, InputState { up: true, ..InputState::default() });
+        assert!(up.maxs().y < position.y, "{:?} isn't above {:?}", up, position);
+        while world.recovery > 0 {
+            world.update(DT, &InputState::default());
+        }
+        let down = swing(&mut world, InputState { down: true, ..InputState::default() });
+        let feet = position.y - 1. + world.hero.object.height;
+        assert!(down.mins().y >= feet, "{:?} isn't below {:?}", down, position);
+        assert!(down.center().x > position.x);
+        assert!(!world.hero.pounding);
+    }
+
+    #[test]
+    fn air_attack_keeps_the_hero_s_momentum() {
+        let mut world = new_world();
+        let hero = world.hero.object.body_handle;
+        place(&mut world, hero, 250., 420., na::Vector2::new(40., 0.));
+        swing(&mut world, InputState::default());
+        assert_eq!(world.attack, &HERO_AIR_ATTACK);
+        let velocity = world.hero.object.linear_velocity(&world.bodies).x;
+        assert!((velocity - 40.).abs() < 1., "moving at {}", velocity);
+    }
+
+    #[test]
+    fn air_attack_momentum_follows_its_behavior() {
+        const KEEP: AttackDef = AttackDef { momentum: MomentumBehavior::Keep, ..HERO_AIR_ATTACK };
+        const HALT: AttackDef = AttackDef { momentum: MomentumBehavior::Halt, ..HERO_AIR_ATTACK };
+        const DAMPEN: AttackDef = AttackDef { momentum: MomentumBehavior::Dampen(0.5), ..HERO_AIR_ATTACK };
+        for &(attack, expected) in &[(&KEEP, 40.), (&HALT, 0.), (&DAMPEN, 20.)] {
+            let mut world = new_world();
+            let hero = world.hero.object.body_handle;
+            place(&mut world, hero, 250., 420., na::Vector2::new(40., 0.));
+            world.start_attack(attack);
+            assert_eq!(world.hero.object.linear_velocity(&world.bodies).x, expected);
+        }
+    }
+
+    #[test]
+    fn ground_attack_halts_the_hero() {
+        let mut world = new_world();
+        stand_hero(&mut world, 150.);
+        world.hero.object.set_linear_velocity(&mut world.bodies, na::Vector2::new(40., 0.));
+        swing(&mut world, InputState::default());
+        assert_eq!(world.attack, &HERO_ATTACK);
+        assert!(world.hero.object.linear_velocity(&world.bodies).x.abs() < 1.);
+    }
+
+    #[test]
+    fn hero_death_slows_time_down() {
+        let mut world = new_world();
+        let enemy = world.enemies[0].object.position(&world.bodies);
+        let hero = world.hero.object.body_handle;
+        world.hero.invulnerable = 0.;
+        world.hero.health = 1;
+        place(&mut world, hero, enemy.x - 21., enemy.y, na::Vector2::new(40., 0.));
+        for _ in 0..60 {
+            world.update(DT, &InputState::default());
+        }
+        assert!(world.hero.is_dead());
+        assert!(world.time_scale.update(DT) < 1.);
+    }
+
+    #[test]
+    fn spawn_protection_keeps_a_respawned_hero_from_harm() {
+        let mut world = new_world();
+        let enemy = world.enemies[0].object.position(&world.bodies);
+        world.hero.respawn(&mut world.bodies, na::Vector2::new(enemy.x - 15., enemy.y));
+        for _ in 0..60 {
+            world.update(DT, &InputState::default());
+            assert_eq!(world.hero.health, HERO_HEALTH);
+        }
+        assert!(world.hero.invulnerable > 0.);
+
+        // The same respawn without protection is hurt straight away.
+        let mut world = new_world();
+        world.hero.spawn_protection = 0.;
+        world.hero.respawn(&mut world.bodies, na::Vector2::new(enemy.x - 15., enemy.y));
+        for _ in 0..60 {
+            world.update(DT, &InputState::default());
+        }
+        assert!(world.hero.health < HERO_HEALTH);
+    }
+
+    // Fastest the hero moves while holding right for `ticks` ticks.
+    fn top_speed(world: &mut GameWorld, ticks: usize) -> f32 {
+        let right = InputState { right: true, ..InputState::default() };
+        (0..ticks).map(|_| {
+            world.update(DT, &right);
+            world.hero.object.linear_velocity(&world.bodies).x
+        }).fold(0., f32::max)
+    }
+
+    // Both start out at walking speed, the hero can only keep going faster when running.
+    #[test]
+    fn walk_toggle_caps_the_speed_at_walk_speed() {
+        let mut world = new_world();
+        stand_hero(&mut world, 150.);
+        world.update(DT, &InputState { toggle_walk: true, ..InputState::default() });
+        assert!(world.hero.walk_toggled);
+        let walk_speed = world.hero.walk_speed;
+        world.hero.object.set_linear_velocity(&mut world.bodies, na::Vector2::new(walk_speed, 0.));
+        let speed = top_speed(&mut world, 30);
+        assert!(speed <= walk_speed + 0.5, "walked at {}", speed);
+
+        let mut world = new_world();
+        stand_hero(&mut world, 150.);
+        world.hero.object.set_linear_velocity(&mut world.bodies, na::Vector2::new(walk_speed, 0.));
+        assert!(top_speed(&mut world, 30) > walk_speed + 5.);
+    }
+
+    // Everything in the dump but the tick and time, which keep counting across restarts.
+    fn level_state(world: &GameWorld) -> String {
+        let dump = world.debug_dump();
+        dump[dump.find("[hero]").unwrap()..].to_owned()
+    }
+
+    #[test]
+    fn restart_matches_a_freshly_loaded_level() {
+        let mut world = new_world();
+        let hero = world.hero.object.body_handle;
+        place(&mut world, hero, 60., 460., na::Vector2::new(20., 0.));
+        world.hero.health = 1;
+        world.enemies[0].hit(1);
+        world.breakables[0].hit(5);
+        let input = InputState { right: true, attack: true, ..InputState::default() };
+        for _ in 0..30 {
+            world.update(DT, &input);
+        }
+        assert!(world.coins_collected > 0);
+        world.restart_level();
+        let fresh = new_world();
+        assert_eq!(level_state(&world), level_state(&fresh));
+        assert_eq!(world.coins_collected, 0);
+        assert_eq!(world.breakables.len(), fresh.breakables.len());
+        assert_eq!(world.coins.len(), fresh.coins.len());
+        assert_eq!(world.tick, 30);
+    }
+
+    #[test]
+    fn connecting_attack_recovers_sooner_than_a_whiff() {
+        let mut world = new_world();
+        stand_hero(&mut world, 150.);
+        swing(&mut world, InputState::default());
+        let whiff = world.recovery;
+        // Close enough to the first breakable block for the swing to reach it.
+        let mut world = new_world();
+        stand_hero(&mut world, 175.);
+        let blocks = world.breakables.len();
+        swing(&mut world, InputState::default());
+        assert_eq!(world.breakables.len(), blocks - 1);
+        assert!(world.recovery < whiff, "{} isn't shorter than {}", world.recovery, whiff);
+    }
+
+    #[test]
+    fn input_delay_applies_a_jump_two_ticks_late() {
+        let settings = UserSettings { input_delay: 2, ..UserSettings::default() };
+        let mut world = GameWorld::new(&settings, Profile::default());
+        stand_hero(&mut world, 150.);
+        let rising = |world: &GameWorld| world.hero.object.linear_velocity(&world.bodies).y < -1.;
+        world.update(DT, &InputState { jump: true, ..InputState::default() });
+        assert!(!rising(&world));
+        world.update(DT, &InputState::default());
+        assert!(!rising(&world));
+        world.update(DT, &InputState::default());
+        assert!(rising(&world));
+    }
+
+    #[test]
+    fn changed_input_delay_takes_effect_on_restart() {
+        let mut world = new_world();
+        world.apply_settings(&UserSettings { input_delay: 3, ..UserSettings::default() });
+        assert_eq!(world.input_delay.delay(), 0);
+        world.restart_level();
+        assert_eq!(world.input_delay.delay(), 3);
+        stand_hero(&mut world, 150.);
+        let rising = |world: &GameWorld| world.hero.object.linear_velocity(&world.bodies).y < -1.;
+        world.update(DT, &InputState { jump: true, ..InputState::default() });
+        for _ in 0..2 {
+            world.update(DT, &InputState::default());
+            assert!(!rising(&world));
+        }
+        world.update(DT, &InputState::default());
+        assert!(rising(&world));
+    }
+
+    #[test]
+    fn pressing_left_mid_swing_keeps_the_attack_facing_right() {
+        let mut world = new_world();
+        let position = stand_hero(&mut world, 150.);
+        swing(&mut world, InputState::default());
+        let left = InputState { left: true, ..InputState::default() };
+        for _ in 0..3 {
+            world.update(DT, &left);
+            let (hitbox, _) = world.hitbox.clone().unwrap();
+            assert!(hitbox.center().x > position.x);
+            assert!(!world.flip);
+        }
+        // The facing is free again on the tick the recovery runs out.
+        while world.recovery > 1 {
+            world.update(DT, &left);
+            assert!(!world.flip);
+        }
+        world.update(DT, &left);
+        assert!(world.flip);
+    }
+
+    #[test]
+    fn hero_climbs_a_ladder_without_gravity_and_jumps_off() {
+        let mut world = new_world();
+        let start = stand_hero(&mut world, 372.);
+        let up = InputState { up: true, ..InputState::default() };
+        for _ in 0..30 {
+            world.update(DT, &up);
+        }
+        assert!(world.hero.climbing);
+        assert_eq!(world.animation, CLIMB_ANIMATION);
+        assert!(!world.hero.object.rigid_body(&world.bodies).gravity_enabled());
+        let position = world.hero.object.position(&world.bodies);
+        assert!(position.y < start.y - 10., "only climbed to {}", position.y);
+
+        world.update(DT, &InputState { jump: true, ..InputState::default() });
+        assert!(!world.hero.climbing);
+        assert!(world.hero.object.rigid_body(&world.bodies).gravity_enabled());
+        assert!(world.hero.object.linear_velocity(&world.bodies).y < 0.);
+    }
+
+    // Velocity after `ticks` ticks of drifting from (x, y) at `velocity`, with no input.
+    fn drift(x: f32, y: f32, velocity: na::Vector2<f32>, ticks: usize) -> (GameWorld, na::Vector2<f32>) {
+        let mut world = new_world();
+        let hero = world.hero.object.body_handle;
+        place(&mut world, hero, x, y, velocity);
+        for _ in 0..ticks {
+            world.update(DT, &InputState::default());
+        }
+        let velocity = world.hero.object.linear_velocity(&world.bodies);
+        (world, velocity)
+    }
+
+    #[test]
+    fn water_slows_the_fall_and_drags_the_hero() {
+        let start = na::Vector2::new(40., 20.);
+        let (world, water) = drift(1000., 425., start, 20);
+        assert!(world.hero.swimming);
+        let (world, air) = drift(250., 400., start, 20);
+        assert!(!world.hero.swimming);
+        assert!(water.y < air.y, "sinking at {} against falling at {}", water.y, air.y);
+        assert!(water.x < air.x - 5., "drifting at {} against {} in the air", water.x, air.x);
+    }
+
+    // Where the hero ends up after standing at `x` and holding `input` for a second.
+    fn hold_in_wind(x: f32, input: InputState) -> (f32, f32) {
+        let mut world = new_world();
+        let start = stand_hero(&mut world, x);
+        for _ in 0..60 {
+            world.update(DT, &input);
+        }
+        (start.x, world.hero.object.position(&world.bodies).x)
+    }
+
+    #[test]
+    fn wind_carries_an_idle_hero() {
+        let (start, end) = hold_in_wind(70., InputState::default());
+        assert!(end > start + 5., "only drifted from {} to {}", start, end);
+    }
+
+    #[test]
+    fn moderate_wind_can_be_walked_against_but_strong_wind_cannot() {
+        let (start, end) = hold_in_wind(70., InputState { left: true, ..InputState::default() });
+        assert!(end < start, "pushed from {} to {}", start, end);
+        let (start, end) = hold_in_wind(1150., InputState { right: true, ..InputState::default() });
+        assert!(end < start, "walked from {} to {}", start, end);
+    }
+
+    #[test]
+    fn tick_counter_and_game_time_follow_the_ticks_run() {
+        let mut world = new_world();
+        for _ in 0..120 {
+            world.update(DT, &InputState::default());
+        }
+        assert_eq!(world.tick, 120);
+        assert!((world.game_time - 120. * DT as f64).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stomping_an_enemy_hurts_it_and_bounces_the_hero() {
+        let mut world = new_world();
+        let enemy = world.enemies[0].object.position(&world.bodies);
+        let health = world.enemies[0].health;
+        let hero = world.hero.object.body_handle;
+        place(&mut world, hero, enemy.x, enemy.y - 22., na::Vector2::new(0., 30.));
+        for _ in 0..30 {
+            let hero_health = world.hero.health;
+            world.update(DT, &InputState::default());
+            if world.enemies[0].health < health {
+                assert!(world.hero.object.linear_velocity(&world.bodies).y < 0., "the hero didn't bounce");
+                assert_eq!(world.hero.health, hero_health);
+                return;
+            }
+        }
+        panic!("the stomp never landed");
+    }
+
+    #[test]
+    fn running_into_an_enemy_hurts_the_hero() {
+        let mut world = new_world();
+        let enemy = world.enemies[0].object.position(&world.bodies);
+        let health = world.enemies[0].health;
+        let hero = world.hero.object.body_handle;
+        world.hero.invulnerable = 0.;
+        place(&mut world, hero, enemy.x - 21., enemy.y, na::Vector2::new(40., 0.));
+        for _ in 0..30 {
+            world.update(DT, &InputState::default());
+        }
+        assert_eq!(world.hero.health, HERO_HEALTH - 1);
+        assert_eq!(world.enemies[0].health, health);
+    }
+
+    #[test]
+    fn debug_hitbox_matches_the_attack_and_facing_while_it_is_out() {
+        for &flip in &[false, true] {
+            let mut world = new_world();
+            let position = stand_hero(&mut world, 150.);
+            world.flip = flip;
+            let hitbox = swing(&mut world, InputState::default());
+            let expected = HERO_ATTACK.hitbox(position, flip);
+            assert_eq!(hitbox.half_extents(), expected.half_extents());
+            assert!((hitbox.center() - expected.center()).norm() < 1., "{:?} isn't {:?}", hitbox, expected);
+            assert_eq!(hitbox.center().x < position.x, flip);
+            for _ in 0..HITBOX_DEBUG_TICKS {
+                world.update(DT, &InputState::default());
+            }
+            assert!(world.hitbox.is_none());
+        }
+    }
+
+    #[test]
+    fn ground_pound_slams_down_and_damages_enemies_on_landing() {
+        let mut world = new_world();
+        let enemy = world.enemies[0].object.position(&world.bodies);
+        let enemies = world.enemies.len();
+        assert_eq!(world.enemies[0].health, POUND_DAMAGE);
+        let hero = world.hero.object.body_handle;
+        place(&mut world, hero, enemy.x - 30., 380., na::Vector2::new(40., 0.));
+        world.update(DT, &InputState { down: true, attack: true, ..InputState::default() });
+        assert!(world.hero.pounding);
+        let velocity = world.hero.object.linear_velocity(&world.bodies);
+        assert_eq!(velocity.x, 0.);
+        assert!(velocity.y >= POUND_SPEED, "falling at {}", velocity.y);
+        for _ in 0..60 {
+            if !world.hero.pounding {
+                break;
+            }
+            world.update(DT, &InputState::default());
+        }
+        assert!(!world.hero.pounding, "never landed");
+        assert_eq!(world.recovery, POUND_RECOVERY);
+        // The shockwave is enough to finish it off.
+        assert_eq!(world.enemies.len(), enemies - 1);
+    }
+
+    #[test]
+    fn dash_only_works_once_unlocked() {
+        let mut world = new_world();
+        stand_hero(&mut world, 150.);
+        let dash = InputState { dash: true, ..InputState::default() };
+        world.update(DT, &dash);
+        assert!(!world.hero.is_dashing());
+        assert!(world.hero.object.linear_velocity(&world.bodies).x.abs() < 1.);
+
+        world.update(DT, &InputState::default());
+        world.profile.unlock_ability(Ability::Dash);
+        world.update(DT, &dash);
+        assert!(world.hero.is_dashing());
+        assert!(world.hero.object.linear_velocity(&world.bodies).x > DASH_SPEED / 2.);
+    }
+
+    #[test]
+    fn reversing_at_speed_skids_before_running_the_other_way() {
+        let mut world = new_world();
+        let position = stand_hero(&mut world, 700.);
+        let hero = world.hero.object.body_handle;
+        place(&mut world, hero, position.x, position.y, na::Vector2::new(SKID_SPEED + 20., 0.));
+        let left = InputState { left: true, ..InputState::default() };
+        world.update(DT, &left);
+        assert!(world.hero.skidding);
+        assert_eq!(world.animation, SKID_ANIMATION);
+        let mut ticks = 0;
+        while world.hero.object.linear_velocity(&world.bodies).x > 0. {
+            assert!(world.hero.skidding, "stopped skidding still moving right");
+            world.update(DT, &left);
+            ticks += 1;
+            assert!(ticks < 600, "never turned around");
+        }
+        world.update(DT, &left);
+        assert!(!world.hero.skidding);
+        assert_eq!(world.animation, RUN_ANIMATION);
+        assert!(world.hero.object.linear_velocity(&world.bodies).x < 0.);
+    }
+
+    // Patrols enemies[0] along a narrow platform far above the level, returning where it ends
+    // up and whether it ever turned around.
+    fn patrol_platform(turns_at_ledges: bool) -> (na::Vector2<f32>, bool) {
+        let mut world = new_world();
+        GameObject::new(550., 200., &mut world.bodies, &mut world.colliders, 30., 5., 0., 0.);
+        world.enemies[0].turns_at_ledges = turns_at_ledges;
+        // Bounds well past both ends, so only the ledges can turn it.
+        world.enemies[0].ai = AiStack::new(AiState::Patrol { left: 400., right: 700. });
+        let enemy = world.enemies[0].object.body_handle;
+        place(&mut world, enemy, 560., 183., na::Vector2::zeros());
+        let mut turned = false;
+        for _ in 0..300 {
+            world.update(DT, &InputState::default());
+            turned |= world.enemies[0].facing() < 0.;
+        }
+        (world.enemies[0].object.position(&world.bodies), turned)
+    }
+
+    #[test]
+    fn patrols_turn_at_ledges_instead_of_walking_off() {
+        let (position, turned) = patrol_platform(true);
+        assert!(turned);
+        assert!(position.y < 200. && position.x > 520. && position.x < 580., "left the platform at {:?}", position);
+        let (position, turned) = patrol_platform(false);
+        assert!(!turned);
+        assert!(position.x > 580., "still on the platform at {:?}", position);
+    }
+
+    #[test]
+    fn attack_lunges_forward_on_its_lunge_tick_only() {
+        let mut world = new_world();
+        stand_hero(&mut world, 150.);
+        swing(&mut world, InputState::default());
+        assert_eq!(world.attack, &HERO_ATTACK);
+        let mut previous = world.hero.object.linear_velocity(&world.bodies).x;
+        while world.recovery > 0 {
+            world.update(DT, &InputState::default());
+            let velocity = world.hero.object.linear_velocity(&world.bodies).x;
+            if world.attack_tick == HERO_ATTACK.lunge_tick {
+                assert!(velocity - previous > HERO_ATTACK.lunge * 0.8, "lunged from {} to {}", previous, velocity);
+            } else {
+                assert!(velocity - previous < 1., "sped up from {} to {} on tick {}", previous, velocity, world.attack_tick);
+            }
+            previous = velocity;
+        }
+    }
+
+    #[test]
+    fn hitting_an_enemy_alerts_allies_in_its_aggro_radius() {
+        let mut world = new_world();
+        let position = stand_hero(&mut world, 400.);
+        world.enemies[0].block_chance = 0.;
+        // Far enough that the ally doesn't see the hero itself.
+        world.enemies[0].aggro_radius = 200.;
+        let (struck, ally) = (world.enemies[0].object.body_handle, world.enemies[2].object.body_handle);
+        place(&mut world, struck, position.x + 25., 455., na::Vector2::zeros());
+        place(&mut world, ally, position.x + 175., 455., na::Vector2::zeros());
+        world.update(DT, &InputState::default());
+        assert!(world.enemies[2].is_patrolling());
+        let health = world.enemies[0].health;
+        swing(&mut world, InputState::default());
+        assert!(world.enemies[0].health < health);
+        assert_eq!(world.enemies[2].ai.current(), AiState::Chase);
+    }
+
+    // Stands the hero at 150 with the first coin 40 to his left, in the magnet's reach.
+    fn coin_near_hero(magnet: bool) -> (GameWorld, DefaultBodyHandle) {
+        let mut world = new_world();
+        if magnet {
+            world.profile.unlock_ability(Ability::CoinMagnet);
+        }
+        let position = stand_hero(&mut world, 150.);
+        let coin = world.coins[0].object.body_handle;
+        place(&mut world, coin, position.x - 40., position.y, na::Vector2::zeros());
+        (world, coin)
+    }
+
+    #[test]
+    fn magnet_pulls_a_nearby_coin_in_and_collects_it() {
+        let (mut world, coin) = coin_near_hero(true);
+        let hero = world.hero.object.position(&world.bodies);
+        let distance = |world: &GameWorld| (world.bodies.rigid_body(coin).unwrap().position().translation.vector - hero).norm();
+        let start = distance(&world);
+        world.update(DT, &InputState::default());
+        assert!(distance(&world) < start);
+        for _ in 0..60 {
+            world.update(DT, &InputState::default());
+        }
+        assert_eq!(world.coins_collected, 1);
+        assert!(world.bodies.rigid_body(coin).is_none());
+
+        let (mut world, coin) = coin_near_hero(false);
+        for _ in 0..60 {
+            world.update(DT, &InputState::default());
+        }
+        assert_eq!(world.coins_collected, 0);
+        assert!(world.bodies.rigid_body(coin).is_some());
+    }
+
+    #[test]
+    fn thrown_stunned_enemy_damages_the_enemy_it_hits() {
+        let mut world = new_world();
+        let position = stand_hero(&mut world, 400.);
+        let (thrown, target) = (world.enemies[2].object.body_handle, world.enemies[0].object.body_handle);
+        place(&mut world, thrown, position.x + 22., 455., na::Vector2::zeros());
+        world.update(DT, &InputState::default());
+        let grab = InputState { grab: true, ..InputState::default() };
+        world.update(DT, &grab);
+        assert!(!world.grab.is_carrying(), "picked up an enemy that wasn't stunned");
+
+        world.update(DT, &InputState::default());
+        world.enemies[2].stun(POUND_STUN);
+        world.update(DT, &grab);
+        assert!(world.enemies[2].carried);
+        world.update(DT, &InputState::default());
+        // Hanging in the air where the throw arcs through.
+        let carried = world.enemies[2].object.position(&world.bodies);
+        world.enemies[0].health = 5;
+        place(&mut world, target, carried.x + 60., carried.y - 30., na::Vector2::zeros());
+        world.update(DT, &grab);
+        assert!(world.enemies[2].thrown);
+        for _ in 0..60 {
+            world.update(DT, &InputState::default());
+        }
+        assert_eq!(world.enemies[0].health, 5 - THROWN_ENEMY_DAMAGE);
+        assert!(!world.enemies[2].thrown);
+    }
+
+    #[test]
+    fn computed_bounds_enclose_every_static_collider_with_the_margin() {
+        let mut world = new_world();
+        // Worked out before the first step, as the camera does.
+        let bounds = world.computed_bounds(25.);
+        world.update(DT, &InputState::default());
+        for (_, collider) in world.colliders.iter() {
+            let is_static = world.bodies.rigid_body(collider.body()).is_some_and(|body| body.augmented_mass().linear == 0.);
+            if collider.is_sensor() || !is_static {
+                continue;
+            }
+            let aabb = collider.shape().aabb(collider.position());
+            assert!(aabb.mins().x >= bounds.x + 25. && aabb.maxs().x <= bounds.x + bounds.w - 25., "{:?} sticks out of {:?}", aabb, (bounds.x, bounds.y, bounds.w, bounds.h));
+            assert!(aabb.mins().y >= bounds.y + 25. && aabb.maxs().y <= bounds.y + bounds.h - 25., "{:?} sticks out of {:?}", aabb, (bounds.x, bounds.y, bounds.w, bounds.h));
+        }
+    }
+
+    // Whether the hero faces left after dying and respawning in a level with `spawn_facing`,
+    // having faced the other way when he died.
+    fn respawns_facing_left(spawn_facing: f32) -> bool {
+        let mut world = new_world();
+        world.spawn_facing = spawn_facing;
+        stand_hero(&mut world, 150.);
+        world.flip = spawn_facing > 0.;
+        world.hero.invulnerable = 0.;
+        world.hero.hurt(HERO_HEALTH);
+        for _ in 0..600 {
+            if !world.hero.is_dead() {
+                break;
+            }
+            world.update(DT, &InputState::default());
+        }
+        assert!(!world.hero.is_dead(), "never respawned");
+        world.flip
+    }
+
+    #[test]
+    fn hero_respawns_facing_the_level_s_spawn_direction() {
+        assert!(respawns_facing_left(-1.));
+        assert!(!respawns_facing_left(1.));
+        assert!(!new_world().flip);
+    }
+
+    // Ticks on which a swing came out while attack was held for `ticks`, and what was swung.
+    fn hold_attack(auto_attack: bool, ticks: u64) -> Vec<(u64, &'static AttackDef)> {
+        let settings = UserSettings { auto_attack, ..UserSettings::default() };
+        let mut world = GameWorld::new(&settings, Profile::default());
+        stand_hero(&mut world, 400.);
+        // Out of reach of the lunges, so every swing whiffs.
+        let (enemy, block) = (world.enemies[0].object.body_handle, world.breakables[1].object.body_handle);
+        place(&mut world, enemy, 2000., 0., na::Vector2::zeros());
+        place(&mut world, block, 2100., 0., na::Vector2::zeros());
+        let mut swings = Vec::new();
+        for tick in 0..ticks {
+            let hitbox = world.hitbox.clone();
+            world.update(DT, &InputState { attack: true, ..InputState::default() });
+            if world.hitbox.is_some() && world.hitbox != hitbox && world.attack_tick == 0 {
+                swings.push((tick, world.attack));
+            }
+        }
+        swings
+    }
+
+    #[test]
+    fn held_attack_repeats_through_the_combo_with_auto_attack() {
+        let swings = hold_attack(true, 150);
+        let def = ComboDef::parse(HERO_COMBO);
+        let steps = &def.steps;
+        assert!(swings.len() >= steps.len() * 2, "only swung {:?}", swings);
+        for (i, pair) in swings.windows(2).enumerate() {
+            let ((tick, attack), (next, _)) = (pair[0], pair[1]);
+            let step = &steps[i % steps.len()];
+            assert_eq!(attack, step.attack);
+            if i % steps.len() + 1 == steps.len() {
+                assert_eq!(next - tick, def.final_recovery as u64);
+            } else {
+                // Into the next step as soon as the cancel window opens, give or take a tick
+                // off the ground after a lunge.
+                let window = (attack.recovery_on_whiff - step.cancel_window) as u64;
+                assert!(next - tick >= window && next - tick <= window + 2, "{} ticks between swings {} and {}", next - tick, i, i + 1);
+            }
+        }
+        assert_eq!(hold_attack(false, 150).len(), 1);
+    }
+
+    fn jump_speed(impulse_scale: f32) -> f32 {
+        let settings = UserSettings { impulse_scale, ..UserSettings::default() };
+        let mut world = GameWorld::new(&settings, Profile::default());
+        stand_hero(&mut world, 150.);
+        world.update(DT, &InputState { jump: true, ..InputState::default() });
+        -world.hero.object.linear_velocity(&world.bodies).y
+    }
+
+    #[test]
+    fn doubling_impulse_scale_doubles_the_jump() {
+        let (normal, doubled) = (jump_speed(1.), jump_speed(2.));
+        assert!(normal > 0.);
+        assert!((doubled - normal * 2.).abs() < 1e-3, "jumped at {} and {}", normal, doubled);
+    }
+
+    #[test]
+    fn teleport_lands_the_hero_still_and_clear_of_the_ground() {
+        let mut world = new_world();
+        let hero = world.hero.object.body_handle;
+        place(&mut world, hero, 150., 300., na::Vector2::new(80., -120.));
+        world.teleport_hero(na::Vector2::new(170., 320.));
+        assert_eq!(world.hero.object.position(&world.bodies), na::Vector2::new(170., 320.));
+        assert_eq!(world.hero.object.linear_velocity(&world.bodies), na::Vector2::zeros());
+
+        let standing = stand_hero(&mut world, 150.);
+        // Half buried in the ground, so it gets nudged back out on top.
+        world.teleport_hero(standing + na::Vector2::new(0., world.hero.object.height));
+        let position = world.hero.object.position(&world.bodies);
+        assert!(position.y < standing.y + world.hero.object.height, "left at {:?}", position);
+        let half_extents = na::Vector2::new(world.hero.object.width, world.hero.object.height);
+        let aabb = AABB::new(na::Point2::from(position - half_extents), na::Point2::from(position + half_extents));
+        assert_eq!(physics::depenetration(&world.geometrical_world, &world.colliders, &aabb, world.hero.object.collider_handle), na::Vector2::zeros());
+    }
+
+    // Swings at enemies[0] right in front of the hero, returning the health it lost and the
+    // hero's speed along his facing afterwards.
+    fn swing_at_blocker(block_chance: f32) -> (u32, f32) {
+        let mut world = new_world();
+        let position = stand_hero(&mut world, 400.);
+        world.enemies[0].block_chance = block_chance;
+        let enemy = world.enemies[0].object.body_handle;
+        place(&mut world, enemy, position.x + 25., 455., na::Vector2::zeros());
+        world.update(DT, &InputState::default());
+        let health = world.enemies[0].health;
+        swing(&mut world, InputState::default());
+        (health - world.enemies[0].health, world.hero.object.linear_velocity(&world.bodies).x)
+    }
+
+    #[test]
+    fn certain_block_takes_no_damage_and_pushes_the_hero_back() {
+        let (lost, speed) = swing_at_blocker(1.);
+        assert_eq!(lost, 0);
+        assert!(speed < -BLOCK_PUSHBACK * 0.8, "pushed back at {}", speed);
+
+        let (lost, speed) = swing_at_blocker(0.);
+        assert_eq!(lost, HERO_ATTACK.damage);
+        assert!(speed > -1., "pushed back at {}", speed);
+    }
+
+    #[test]
+    fn debug_dump_records_the_tick_and_the_hero_position() {
+        let mut world = new_world();
+        let position = stand_hero(&mut world, 150.);
+        let dump = world.debug_dump();
+        let line = |key: &str, section: &str| dump.split("\n\n")
+            .find(|part| part.starts_with(section))
+            .and_then(|part| part.lines().find(|line| line.starts_with(key)))
+            .map(str::to_string);
+        assert_eq!(line("tick = ", "tick"), Some(format!("tick = {}", world.tick)));
+        assert_eq!(line("position = ", "[hero]"), Some(format!("position = {}, {}", position.x, position.y)));
+        assert_eq!(dump.matches("\n[enemy ").count(), world.enemies.len());
+    }
+
+    #[test]
+    fn hero_teleported_off_the_conveyor_isnt_pushed_back() {
+        let mut world = new_world();
+        let hero = world.hero.object.body_handle;
+        place(&mut world, hero, 300., 460., na::Vector2::zeros());
+        for _ in 0..10 {
+            world.update(DT, &InputState::default());
+        }
+        assert!(world.hero.object.linear_velocity(&world.bodies).x > 30., "not riding the belt");
+        world.teleport_hero(na::Vector2::new(150., 300.));
+        world.update(DT, &InputState::default());
+        assert_eq!(world.hero.object.linear_velocity(&world.bodies).x, 0.);
+    }
+
+    // Stands the hero at `x` on a platform spanning 620..780 high above the level, then holds
+    // `input` for a second. Returns how far below the hero the camera ended up.
+    fn camera_below_hero(x: f32, input: InputState) -> f32 {
+        let mut world = new_world();
+        GameObject::new(700., 250., &mut world.bodies, &mut world.colliders, 80., 5., 0., 0.);
+        let hero = world.hero.object.body_handle;
+        place(&mut world, hero, x, 234., na::Vector2::zeros());
+        for _ in 0..120 {
+            world.update(DT, &InputState::default());
+        }
+        for _ in 0..60 {
+            world.update(DT, &input);
+        }
+        world.camera.target.y() - world.hero.object.position(&world.bodies).y
+    }
+
+    #[test]
+    fn camera_peeks_only_for_an_idle_hero_at_a_ledge() {
+        let down = InputState { down: true, ..InputState::default() };
+        let resting = camera_below_hero(765., InputState::default());
+        assert!(camera_below_hero(765., down) > resting + 30.);
+        // Away from the edges.
+        assert!((camera_below_hero(700., down) - resting).abs() < 5.);
+        // Running back from the edge.
+        assert!((camera_below_hero(765., InputState { left: true, ..down }) - resting).abs() < 5.);
+    }
+
+    #[test]
+    fn ragdoll_tumbles_then_is_removed_from_the_world_once_faded() {
+        let settings = UserSettings { enemy_ragdolls: true, ..UserSettings::default() };
+        let mut world = GameWorld::new(&settings, Profile::default());
+        let position = stand_hero(&mut world, 400.);
+        let enemy = &world.enemies[2];
+        let (body, collider) = (enemy.object.body_handle, enemy.object.collider_handle);
+        place(&mut world, body, position.x + 25., 455., na::Vector2::zeros());
+        world.update(DT, &InputState::default());
+        let enemies = world.enemies.len();
+        swing(&mut world, InputState::default());
+        world.update(DT, &InputState::default());
+        assert_eq!(world.enemies.len(), enemies - 1);
+        assert_eq!(world.corpses.len(), 1);
+        let ragdoll = world.bodies.rigid_body(body).expect("the ragdoll has no body");
+        assert!(ragdoll.augmented_mass().linear > 0.);
+        assert!(ragdoll.velocity().angular.abs() > 1.);
+        assert!(world.colliders.get(collider).is_some());
+
+        let fade = (RAGDOLL_FADE_TIME / DT) as usize;
+        for _ in 0..fade - 5 {
+            world.update(DT, &InputState::default());
+        }
+        assert!(world.bodies.get(body).is_some());
+        for _ in 0..7 {
+            world.update(DT, &InputState::default());
+        }
+        assert!(world.corpses.is_empty());
+        assert!(world.bodies.get(body).is_none());
+        assert!(world.colliders.get(collider).is_none());
+    }
+}